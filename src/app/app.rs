@@ -1,383 +1,1530 @@
 use std::vec;
 
 use crate::app::tui::*;
-use crate::{fetch_initial_data, get_expeditions, prelude::*};
+use crate::config::{Config, KeyMap};
+use crate::prelude::*;
+use arriva::utils::{search_stops, Backend};
+use arriva::ArrivaClient;
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use chrono::{DateTime, Days, Local, NaiveDate, TimeDelta};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     prelude::*,
     symbols::border,
     widgets::{block::*, *},
 };
-use std::borrow::Cow;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
 
+use super::calendar::DatePicker;
+use super::components::{
+    Action, Component, ExpeditionTableComponent, KeyHintBar, ListedRoute, RouteListComponent,
+    StopListComponent, TitleBar,
+};
+use super::event::{self, Event, EventHandler, Message};
+use super::screen::{Context, Screen, Transition};
 use super::stateful_list::StatefulList;
 
-#[derive(Debug, Default)]
+/// Which of the two expedition tables has the focus
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    Outward,
+    Return,
+}
+
+impl Direction {
+    pub fn other(self) -> Self {
+        match self {
+            Direction::Outward => Direction::Return,
+            Direction::Return => Direction::Outward,
+        }
+    }
+}
+
+/// Which of the start screen lists has the focus
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StartPanel {
+    #[default]
+    Favourites,
+    Recent,
+}
+
+impl StartPanel {
+    pub fn other(self) -> Self {
+        match self {
+            StartPanel::Favourites => StartPanel::Recent,
+            StartPanel::Recent => StartPanel::Favourites,
+        }
+    }
+}
+
+/// What the background refresh of the cached stops is doing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StopsRefresh {
+    #[default]
+    Idle,
+    Running,
+    Failed,
+}
+
+const TICK_RATE: Duration = Duration::from_millis(100);
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+#[derive(Debug)]
 pub struct App {
+    pub stops: Vec<Stop>,
+    pub search: String,
     pub from_stops: StatefulList<Stop>,
     pub to_stops: StatefulList<Stop>,
+    pub screen: Screen,
     pub desired_stops: (Option<Stop>, Option<Stop>),
     pub expeditions: Option<(StatefulList<Expedition>, StatefulList<Expedition>)>,
-    pub ready_for_expeditions: bool,
+    pub focus: Direction,
+    pub details: Option<Expedition>,
+    details_scroll: u16,
+    pub date: NaiveDate,
+    pub date_picker: DatePicker,
+    pub decimal_separator: DecimalSeparator,
+    pub favourites: Favourites,
+    // the starred routes whose stops are still served, as shown in the panel
+    pub favourite_routes: StatefulList<Route>,
+    // where favourites are saved, `None` keeps them in memory only
+    favourites_path: Option<PathBuf>,
+    pub history: History,
+    // the searches whose stops are still served, newest first
+    pub recent_searches: StatefulList<HistoryEntry>,
+    // where the history is saved, `None` keeps it in memory only
+    history_path: Option<PathBuf>,
+    pub start_focus: StartPanel,
+    pub keys: KeyMap,
+    // when the cached stops were fetched, `None` when they do not come from the cache
+    pub stops_fetched_at: Option<DateTime<Local>>,
+    pub stops_refresh: StopsRefresh,
+    pub error: Option<Error>,
     pub exit: bool,
+    client: ArrivaClient<Backend>,
+    // background fetch in flight, tagged with an id so cancelled results are ignored
+    fetch: Option<JoinHandle<()>>,
+    fetch_id: usize,
+    spinner: usize,
+    sender: UnboundedSender<Message>,
+    pub(crate) receiver: Option<UnboundedReceiver<Message>>,
 }
 
 impl App {
-    pub fn new(stops: Vec<Stop>) -> Self {
+    pub fn new(stops: Vec<Stop>, client: ArrivaClient<Backend>) -> Self {
+        let (sender, receiver) = event::channel();
         App {
             from_stops: StatefulList::with_items(stops.clone()),
-            to_stops: StatefulList::with_items(stops),
+            to_stops: StatefulList::with_items(stops.clone()),
+            stops,
+            search: String::new(),
+            screen: Screen::ChooseOrigin,
             desired_stops: (None, None),
             expeditions: None,
-            ready_for_expeditions: false,
+            focus: Direction::Outward,
+            details: None,
+            details_scroll: 0,
+            date: Local::now().date_naive(),
+            date_picker: DatePicker::default(),
+            decimal_separator: DecimalSeparator::from_env(),
+            favourites: Favourites::default(),
+            favourite_routes: StatefulList::with_items(vec![]),
+            favourites_path: None,
+            history: History::default(),
+            recent_searches: StatefulList::with_items(vec![]),
+            history_path: None,
+            start_focus: StartPanel::Favourites,
+            keys: KeyMap::default(),
+            stops_fetched_at: None,
+            stops_refresh: StopsRefresh::Idle,
+            error: None,
             exit: false,
+            client,
+            fetch: None,
+            fetch_id: 0,
+            spinner: 0,
+            sender,
+            receiver: Some(receiver),
+        }
+    }
+
+    /// Applies the keys and defaults of the config file,
+    /// failing when the default origin is not a known stop
+    pub fn with_config(mut self, config: &Config) -> Result<Self> {
+        self.keys = config.keys.clone();
+        self.date = Local::now()
+            .date_naive()
+            .checked_add_days(Days::new(config.defaults.date_offset.into()))
+            .unwrap_or(self.date);
+
+        if let Some(origin) = config.defaults.origin {
+            let stop = self
+                .stops
+                .iter()
+                .find(|stop| stop.get_parada() == origin)
+                .cloned()
+                .ok_or_else(|| Error::UnknownStop(origin.to_string()))?;
+            self.from_stops.select_item(&stop);
+        }
+        Ok(self)
+    }
+
+    /// Starts on the start screen when any starred route can be recalled
+    pub fn with_favourites(mut self, favourites: Favourites, path: Option<PathBuf>) -> Self {
+        self.favourites = favourites;
+        self.favourites_path = path;
+        self.refresh_favourites();
+        self.show_start_screen();
+        self
+    }
+
+    /// Starts on the start screen when any recent search can be run again
+    pub fn with_history(mut self, history: History, path: Option<PathBuf>) -> Self {
+        self.history = history;
+        self.history_path = path;
+        self.refresh_recent_searches();
+        self.show_start_screen();
+        self
+    }
+
+    /// Shows the age of the cached stops, asking for them again in the background
+    /// when `refresh` is set. Without a cache path the stops are not from the cache
+    pub fn with_stop_cache(
+        mut self,
+        cache: StopCache,
+        path: Option<PathBuf>,
+        refresh: bool,
+    ) -> Self {
+        let Some(path) = path else {
+            return self;
+        };
+        self.stops_fetched_at = Some(cache.fetched_at);
+        if refresh {
+            self.stops_refresh = StopsRefresh::Running;
+            let client = self.client.clone();
+            let sender = self.sender.clone();
+            // saved here, so writing the catalogue never holds up the event loop
+            tokio::spawn(async move {
+                let result = client.refresh_stops(Some(&cache)).await;
+                if let Ok(cache) = &result {
+                    cache.save_or_warn(&path);
+                }
+                let _ = sender.send(Message::StopsRefreshed(result));
+            });
+        }
+        self
+    }
+
+    fn show_start_screen(&mut self) {
+        if self.has_saved_routes() {
+            self.screen = Screen::Start;
+            self.fix_start_focus();
         }
     }
 
+    fn has_saved_routes(&self) -> bool {
+        !self.favourite_routes.items.is_empty() || !self.recent_searches.items.is_empty()
+    }
+
     // runs the application's main loop until the user quits
     pub async fn run(&mut self, terminal: &mut Tui) -> Result<()> {
+        let Some(receiver) = self.receiver.take() else {
+            return Err(Error::Generic("App is already running".to_string()));
+        };
+        let mut events = EventHandler::new(receiver, TICK_RATE);
+
         while !self.exit {
-            if self.ready_for_expeditions {
-                let from = self
-                    .desired_stops
-                    .0
-                    .as_ref()
-                    .expect("Missing from stop reference");
-                let to = self
-                    .desired_stops
-                    .1
-                    .as_ref()
-                    .expect("Missing to stop reference");
-                let expeditions_value: Value = match get_expeditions((&from, &to), None).await {
-                    Ok(expeditions) => expeditions,
-                    Err(err) => panic!("Error fetching expeditions: \n {:?}", err),
-                };
+            terminal.draw(|frame| self.render_frame(frame))?;
+            match events.next().await? {
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    self.handle_key_event(key_event)
+                }
+                Event::Tick if self.is_loading() => self.spinner = self.spinner.wrapping_add(1),
+                Event::Message(message) => self.handle_message(message),
+                _ => {}
+            }
+        }
+        self.cancel_fetch();
+        Ok(())
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.fetch.is_some()
+    }
+
+    // fetches the expeditions for the desired stops in the background,
+    // the result comes back as a message through the event loop
+    fn start_fetch(&mut self) {
+        let (Some(from), Some(to)) = (self.desired_stops.0.clone(), self.desired_stops.1.clone())
+        else {
+            return;
+        };
+
+        self.cancel_fetch();
+        self.error = None;
+        self.expeditions = None;
+        self.fetch_id = self.fetch_id.wrapping_add(1);
+
+        let id = self.fetch_id;
+        let date = self.date;
+        let client = self.client.clone();
+        let sender = self.sender.clone();
+        self.fetch = Some(tokio::spawn(async move {
+            let result = client.expeditions(&from, &to, date).await;
+            let _ = sender.send(Message::ExpeditionsLoaded(id, result));
+        }));
+    }
 
-                let expeditions_vecs: (Vec<Expedition>, Vec<Expedition>) =
-                    match deserialize_expeditions(expeditions_value) {
-                        Ok(expeditions) => expeditions,
-                        Err(err) => panic!("Error deserializing expeditions: \n {:?}", err),
-                    };
+    fn cancel_fetch(&mut self) {
+        if let Some(fetch) = self.fetch.take() {
+            fetch.abort();
+        }
+    }
 
-                self.expeditions = Some((
-                    StatefulList::with_items(expeditions_vecs.0),
-                    StatefulList::with_items(expeditions_vecs.1),
-                ));
+    pub(crate) fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::ExpeditionsLoaded(id, result) if id == self.fetch_id => {
+                self.fetch = None;
+                match result {
+                    Ok((outward, ret)) => {
+                        self.expeditions = Some((
+                            StatefulList::with_first_selected(outward),
+                            StatefulList::with_first_selected(ret),
+                        ));
+                        self.record_search();
+                    }
+                    Err(err) => {
+                        self.error = Some(err);
+                        self.go(Transition::Fail);
+                    }
+                }
+            }
+            // results of a cancelled fetch
+            Message::ExpeditionsLoaded(_, _) => {}
+            Message::StopsRefreshed(Ok(cache)) => {
+                self.stops_refresh = StopsRefresh::Idle;
+                self.stops_fetched_at = Some(cache.fetched_at);
+                self.replace_stops(cache.stops);
+            }
+            // the cached stops are still usable, the title tells they are old
+            Message::StopsRefreshed(Err(err)) => {
+                tracing::warn!(error = %err, "could not refresh the stops");
+                self.stops_refresh = StopsRefresh::Failed;
             }
-            terminal.draw(|frame| self.render_frame(frame))?;
-            self.handle_events()?;
         }
-        Ok(())
     }
 
-    fn render_frame(&self, frame: &mut Frame) {
-        let main_constraints = vec![
+    // moves to the screen `transition` leads to, dropping what the left screen owned.
+    // List positions are kept, so going back shows the stop that was chosen.
+    fn go(&mut self, transition: Transition) {
+        let from = self.screen;
+        let context = Context {
+            has_results: self.expeditions.is_some(),
+            has_saved_routes: self.has_saved_routes(),
+        };
+        self.screen = from.next(transition, context);
+
+        match (from, self.screen) {
+            (Screen::ChooseDestination, Screen::ChooseOrigin) => self.desired_stops.0 = None,
+            (Screen::ChooseDate, Screen::ChooseDestination) => self.desired_stops.1 = None,
+            (Screen::Results, Screen::ChooseDate) if transition == Transition::Back => {
+                self.cancel_fetch();
+                self.expeditions = None;
+            }
+            _ => {}
+        }
+        if self.screen == Screen::ChooseDate && from != Screen::ChooseDate {
+            self.date_picker = DatePicker::new(self.date);
+        }
+        if self.screen != Screen::Details {
+            self.details = None;
+        }
+        if self.screen != Screen::Error {
+            self.error = None;
+        }
+    }
+
+    pub(crate) fn render_frame(&mut self, frame: &mut Frame) {
+        let main_chunks = Layout::vertical([
             Constraint::Percentage(5),
             Constraint::Percentage(90),
             Constraint::Percentage(5),
-        ];
-        let main_chunks = Layout::vertical(main_constraints).split(frame.size());
-        let title = Title::from(
-            " Arriva Terminal User Interface "
-                .fg(PRIMARY_COLOR_RTT)
-                .bold(),
-        );
-        let constraints = vec![
+        ])
+        .split(frame.size());
+
+        TitleBar::new(" Arriva Terminal User Interface ")
+            .status(self.stops_age())
+            .render(frame, main_chunks[0]);
+        match self.screen {
+            Screen::Start => self.render_start(frame, main_chunks[1]),
+            Screen::ChooseOrigin | Screen::ChooseDestination => {
+                self.render_stop_selection(frame, main_chunks[1])
+            }
+            Screen::ChooseDate => self.render_date_picker(frame, main_chunks[1]),
+            Screen::Details => self.render_details(frame, main_chunks[1]),
+            Screen::Results | Screen::Error => self.render_results(frame, main_chunks[1]),
+        }
+        self.key_hints().render(frame, main_chunks[2]);
+
+        if let Some(error) = &self.error {
+            self.render_error_popup(frame, error);
+        }
+    }
+
+    fn key_hints(&self) -> KeyHintBar {
+        let keys = &self.keys;
+        let hints = KeyHintBar::new().hint("Move", "<Up/Down>");
+
+        match self.screen {
+            Screen::Start => hints
+                .key("Quit", keys.quit)
+                .hint("Open", "<Enter>")
+                .key("Remove", keys.remove)
+                .key("Switch", keys.switch_focus)
+                .key("Search stops", keys.back),
+            Screen::ChooseOrigin | Screen::ChooseDestination => hints
+                .hint("Quit", "<Ctrl+C>")
+                .hint("Select", "<Enter>")
+                .hint("Copy From", "<Tab>"),
+            Screen::Results => hints
+                .key("Quit", keys.quit)
+                .hint("Select", "<Enter>")
+                .key("Back", keys.back)
+                .key("Date", keys.change_date)
+                .key("Swap", keys.swap)
+                .key("Switch", keys.switch_focus)
+                .key(
+                    if self.is_favourite() {
+                        "Unstar"
+                    } else {
+                        "Star"
+                    },
+                    keys.star,
+                ),
+            _ => hints.key("Quit", keys.quit).hint("Select", "<Enter>"),
+        }
+    }
+
+    fn render_start(&mut self, frame: &mut Frame, area: Rect) {
+        let (favourites_area, recent_area) = columns(area);
+        RouteListComponent::new(&mut self.favourite_routes, " Favourites ")
+            .focused(self.start_focus == StartPanel::Favourites)
+            .render(frame, favourites_area);
+        RouteListComponent::new(&mut self.recent_searches, " Recent ")
+            .focused(self.start_focus == StartPanel::Recent)
+            .render(frame, recent_area);
+    }
+
+    fn render_stop_selection(&mut self, frame: &mut Frame, area: Rect) {
+        // the stop lists get a search input on top of them
+        let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).split(area);
+        self.render_search(frame, chunks[0]);
+
+        let (from_area, to_area) = columns(chunks[1]);
+        StopListComponent::new(&mut self.from_stops, "From: ")
+            .focused(self.screen == Screen::ChooseOrigin)
+            .render(frame, from_area);
+        StopListComponent::new(&mut self.to_stops, "To: ")
+            .focused(self.screen == Screen::ChooseDestination)
+            .render(frame, to_area);
+    }
+
+    fn render_results(&mut self, frame: &mut Frame, area: Rect) {
+        let Some((outward, ret)) = self.expeditions.as_mut() else {
+            if self.is_loading() {
+                self.render_spinner(frame, area);
+            }
+            return;
+        };
+
+        let date = self.date.format(DATE_FORMAT);
+        let (outward_area, return_area) = columns(area);
+        ExpeditionTableComponent::new(outward, format!("IDA · {}", date), self.decimal_separator)
+            .focused(self.focus == Direction::Outward)
+            .render(frame, outward_area);
+        ExpeditionTableComponent::new(ret, format!("VOLTA · {}", date), self.decimal_separator)
+            .focused(self.focus == Direction::Return)
+            .render(frame, return_area);
+    }
+
+    fn render_search(&self, frame: &mut Frame, area: Rect) {
+        let area = Layout::horizontal([
             Constraint::Percentage(3),
-            Constraint::Percentage(47),
-            Constraint::Percentage(0),
-            Constraint::Percentage(47),
+            Constraint::Percentage(94),
             Constraint::Percentage(3),
-        ];
-        let chunks = Layout::horizontal(constraints).split(main_chunks[1]);
-
-        let instructions = Title::from(Line::from(vec![
-            " Decrement ".into(),
-            "<Up>".fg(PRIMARY_COLOR_RTT).bold(),
-            " Increment ".into(),
-            "<Down>".fg(PRIMARY_COLOR_RTT).bold(),
-            " Quit ".into(),
-            "<Q> ".fg(PRIMARY_COLOR_RTT).bold(),
-            " Select ".into(),
-            "<Enter> ".fg(PRIMARY_COLOR_RTT).bold(),
-        ]));
-
-        let title_block = Block::default()
-            .borders(Borders::NONE)
-            .border_set(border::THICK)
-            .title(title.alignment(Alignment::Center).position(Position::Top));
-
-        let instructions_block = Block::default()
-            .borders(Borders::NONE)
-            .border_set(border::THICK)
-            .title(
-                instructions
-                    .alignment(Alignment::Center)
-                    .position(Position::Bottom),
-            );
+        ])
+        .split(area)[1];
+        let title = match self.screen {
+            Screen::ChooseOrigin => " Search From: ",
+            _ => " Search To: ",
+        };
+        let can_go_back = self.screen != Screen::ChooseOrigin || self.has_saved_routes();
+        let esc_hint = if self.search.is_empty() && can_go_back {
+            " Back "
+        } else {
+            " Clear "
+        };
 
-        frame.render_widget(title_block, main_chunks[0]);
-        if (!self.ready_for_expeditions) {
-            let from_list: Vec<ListItem> = self
-                .from_stops
-                .items
-                .iter()
-                .map(|i| {
-                    ListItem::new(text::Line::from(vec![
-                        Span::raw(i.get_parada().to_string()).fg(SECUNDARY_COLOR_RTT),
-                        Span::raw(" - "),
-                        Span::raw(i.get_nombre()).fg(PRIMARY_COLOR_RTT),
+        let search = Paragraph::new(Line::from(vec![
+            Span::styled(self.search.as_str(), theme().muted),
+            Span::styled("█", theme().highlight),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Title::from(title))
+                .title(
+                    Title::from(Line::from(vec![
+                        esc_hint.into(),
+                        Span::styled("<Esc> ", theme().highlight),
                     ]))
-                })
-                .collect();
-            let to_list: Vec<ListItem> = self
-                .to_stops
-                .items
+                    .alignment(Alignment::Right),
+                ),
+        );
+
+        frame.render_widget(search, area);
+    }
+
+    fn render_date_picker(&self, frame: &mut Frame, area: Rect) {
+        let area = centered_rect(50, 60, area);
+        let route = match &self.desired_stops {
+            (Some(from), Some(to)) => format!(" {} -> {} ", from.get_nombre(), to.get_nombre()),
+            _ => String::new(),
+        };
+
+        let instructions = KeyHintBar::new()
+            .hint("Day", "<Left/Right>")
+            .hint("Week", "<Up/Down>")
+            .hint("Month", "<PgUp/PgDn>")
+            .hint("Today", "<T>")
+            .key("Back", self.keys.back);
+
+        let calendar = self.date_picker.widget().block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Title::from(Span::styled(" Travel date ", theme().title)))
+                .title(Title::from(route).alignment(Alignment::Right))
+                .title(instructions.title()),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(calendar, area);
+    }
+
+    fn render_details(&self, frame: &mut Frame, area: Rect) {
+        let Some(expedition) = &self.details else {
+            return;
+        };
+        let area = centered_rect(80, 100, area);
+        let field = |name: &str, value: String| {
+            Line::from(vec![
+                Span::styled(format!("{:<16}", name), theme().title),
+                Span::styled(value, theme().muted),
+            ])
+        };
+
+        let fare = expedition.get_fare();
+        let mut lines = vec![
+            field("Línea", expedition.get_name()),
+            field(
+                "Salida",
+                expedition
+                    .get_departure()
+                    .format("%d-%m-%Y %H:%M (%:z)")
+                    .to_string(),
+            ),
+            field(
+                "Chegada",
+                expedition
+                    .get_arrival()
+                    .format("%d-%m-%Y %H:%M (%:z)")
+                    .to_string(),
+            ),
+            field("Duración", format_duration(expedition.get_duration())),
+            field("Tarifa básica", fare.basic.format(self.decimal_separator)),
+        ];
+        lines.extend(
+            fare.tariffs
                 .iter()
-                .map(|i| {
-                    ListItem::new(text::Line::from(vec![
-                        Span::raw(i.get_parada().to_string()).fg(SECUNDARY_COLOR_RTT),
-                        Span::raw(" - "),
-                        Span::raw(i.get_nombre()).fg(PRIMARY_COLOR_RTT),
-                    ]))
-                })
-                .collect();
-            let from_block = List::new(from_list)
-                .block(
-                    Block::default()
-                        .borders(match self.desired_stops {
-                            (None, None) => Borders::ALL,
-                            (Some(_), None) => Borders::NONE,
-                            _ => Borders::NONE,
-                        })
-                        .title(Title::from("From: ")),
-                )
-                .highlight_style(
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .add_modifier(Modifier::ITALIC),
-                )
-                .highlight_symbol("->  ");
-
-            let to_block = List::new(to_list)
-                .block(
-                    Block::default()
-                        .borders(match self.desired_stops {
-                            (None, None) => Borders::NONE,
-                            (Some(_), None) => Borders::ALL,
-                            _ => Borders::NONE,
-                        })
-                        .title(Title::from("To: ")),
-                )
-                .highlight_style(
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .add_modifier(Modifier::ITALIC),
-                )
-                .highlight_symbol("->  ");
-
-            frame.render_stateful_widget(from_block, chunks[1], &mut self.from_stops.state.clone());
-            frame.render_stateful_widget(to_block, chunks[3], &mut self.to_stops.state.clone());
-        } else {
-            if let Some((outward_list, return_list)) = &self.expeditions {
-                let outward_rows = outward_list.items.iter().map(|i| {
-                    Row::new(vec![
-                        Cell::from(i.get_name()).style(
-                            Style::default()
-                                .fg(PRIMARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::BOLD),
-                        ),
-                        Cell::from(i.get_departure()).style(
-                            Style::default()
-                                .fg(SECUNDARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::ITALIC),
-                        ),
-                        Cell::from(i.get_arrival()).style(
-                            Style::default()
-                                .fg(SECUNDARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::ITALIC),
-                        ),
-                        Cell::from(i.get_cost()).style(
-                            Style::default()
-                                .fg(PRIMARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::ITALIC),
-                        ),
-                    ])
-                });
-
-                let return_rows = return_list.items.iter().map(|i| {
-                    Row::new(vec![
-                        Cell::from(i.get_name()).style(
-                            Style::default()
-                                .fg(PRIMARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::BOLD),
-                        ),
-                        Cell::from(i.get_departure()).style(
-                            Style::default()
-                                .fg(SECUNDARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::ITALIC),
-                        ),
-                        Cell::from(i.get_arrival()).style(
-                            Style::default()
-                                .fg(SECUNDARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::ITALIC),
-                        ),
-                        Cell::from(i.get_cost()).style(
-                            Style::default()
-                                .fg(PRIMARY_COLOR_RTT)
-                                .add_modifier(style::Modifier::ITALIC),
-                        ),
-                    ])
-                });
-
-                let widths = [
-                    Constraint::Percentage(55),
-                    Constraint::Percentage(15),
-                    Constraint::Percentage(15),
-                    Constraint::Percentage(15),
-                ];
-
-                // Creamos las tablas con las filas correspondientes
-                let outward_table = Table::new(outward_rows, widths)
-                    .block(
-                        Block::default().borders(Borders::ALL).title(
-                            Span::raw("IDA").style(
-                                Style::default()
-                                    .fg(PRIMARY_COLOR_RTT)
-                                    .add_modifier(style::Modifier::BOLD),
-                            ),
-                        ),
-                    )
-                    .header(
-                        Row::new(vec!["LINEA, SALIDA, CHEGADA, COSTE(€)"]).style(
-                            Style::default()
-                                .fg(Color::Black)
-                                .add_modifier(style::Modifier::BOLD),
-                        ),
-                    )
-                    .widths(&[
-                        Constraint::Percentage(55),
-                        Constraint::Percentage(15),
-                        Constraint::Percentage(15),
-                        Constraint::Percentage(15),
-                    ]);
-
-                let return_table = Table::new(return_rows, widths)
-                    .block(
-                        Block::default().borders(Borders::ALL).title(
-                            Span::raw("VOLTA").style(
-                                Style::default()
-                                    .fg(PRIMARY_COLOR_RTT)
-                                    .add_modifier(style::Modifier::BOLD),
-                            ),
-                        ),
-                    )
-                    .header(
-                        Row::new(vec!["LINEA, SALIDA, CHEGADA, COSTE(€)"]).style(
-                            Style::default()
-                                .fg(Color::Black)
-                                .add_modifier(style::Modifier::BOLD),
-                        ),
-                    )
-                    .widths(&[
-                        Constraint::Percentage(55),
-                        Constraint::Percentage(15),
-                        Constraint::Percentage(15),
-                        Constraint::Percentage(15),
-                    ]);
-
-                // Renderizamos las tablas en el frame
-                frame.render_widget(outward_table, chunks[1]);
-                frame.render_widget(return_table, chunks[3]);
+                .map(|(name, amount)| field(name, amount.format(self.decimal_separator))),
+        );
+
+        // everything else Arriva returned, as is
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Todos os campos", theme().title)));
+        if let Some(raw) = expedition.get_raw().as_object() {
+            lines.extend(
+                raw.iter()
+                    .map(|(key, value)| field(key, format_raw_value(value))),
+            );
+        }
+
+        let instructions = KeyHintBar::new()
+            .hint("Scroll", "<Up/Down>")
+            .key("Back", self.keys.back);
+
+        let details = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.details_scroll, 0))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(Title::from(Span::styled(
+                        " Expedition details ",
+                        theme().title,
+                    )))
+                    .title(instructions.title()),
+            );
+
+        frame.render_widget(details, area);
+    }
+
+    fn render_spinner(&self, frame: &mut Frame, area: Rect) {
+        let spinner = Paragraph::new(Line::from(vec![
+            Span::styled(
+                SPINNER_FRAMES[self.spinner % SPINNER_FRAMES.len()],
+                theme().highlight,
+            ),
+            " Fetching expeditions... ".into(),
+            Span::styled(format!("<{}>", self.keys.back), theme().highlight),
+            " Cancel".into(),
+        ]))
+        .alignment(Alignment::Center);
+
+        frame.render_widget(spinner, centered_rect(100, 10, area));
+    }
+
+    fn render_error_popup(&self, frame: &mut Frame, error: &Error) {
+        let area = centered_rect(60, 30, frame.size());
+
+        let instructions = KeyHintBar::new()
+            .key("Retry", self.keys.retry)
+            .key("Back", self.keys.back);
+
+        let popup = Paragraph::new(error.to_string())
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::THICK)
+                    .border_style(theme().error)
+                    .title(Title::from(Span::styled(
+                        " Error ",
+                        theme().error.add_modifier(Modifier::BOLD),
+                    )))
+                    .title(instructions.title()),
+            );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
+    }
+
+    pub(crate) fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let code = key_event.code;
+        let back = self.is_back(&code);
+        let ctrl_c =
+            key_event.modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('c');
+
+        // while choosing stops every character goes to the search input
+        if ctrl_c || (!self.screen.is_choosing_stops() && self.keys.quit == code) {
+            self.exit = true;
+            return;
+        }
+
+        match self.screen {
+            Screen::Start => self.handle_start(&code),
+            Screen::ChooseOrigin | Screen::ChooseDestination => self.handle_stop_selection(&code),
+            Screen::ChooseDate => self.handle_date_selection(&code),
+            // while loading only cancelling is possible
+            Screen::Results if self.is_loading() => {
+                if back {
+                    self.go(Transition::Back);
+                }
             }
+            Screen::Results => self.handle_results(&code),
+            Screen::Details => self.handle_details(&code),
+            Screen::Error => match code {
+                code if self.keys.retry == code => {
+                    self.go(Transition::Retry);
+                    self.start_fetch();
+                }
+                KeyCode::Enter => self.go(Transition::Back),
+                _ if back => self.go(Transition::Back),
+                _ => {}
+            },
         }
-        frame.render_widget(instructions_block, main_chunks[2]);
-    }
-
-    fn handle_events(&mut self) -> Result<()> {
-        match event::read()? {
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                if key_event.code == KeyCode::Char('q') {
-                    self.exit = true;
-                } else if !self.ready_for_expeditions {
-                    match self.desired_stops {
-                        (None, None) => {
-                            self.desired_stops.0 =
-                                match self.from_stops.handle_events(&key_event.code) {
-                                    Some(stop) => Some(stop),
-                                    None => None,
-                                };
-                        }
-                        (Some(_), None) => {
-                            self.desired_stops.1 =
-                                match self.to_stops.handle_events(&key_event.code) {
-                                    Some(stop) => Some(stop),
-                                    None => None,
-                                };
-                            if self.desired_stops.1 != None {
-                                self.ready_for_expeditions = true;
-                            }
+    }
+
+    // Backspace goes back too, whatever key is configured
+    fn is_back(&self, code: &KeyCode) -> bool {
+        self.keys.back == *code || *code == KeyCode::Backspace
+    }
+
+    // the arrows switch between side by side lists too
+    fn is_switch_focus(&self, code: &KeyCode) -> bool {
+        self.keys.switch_focus == *code || matches!(code, KeyCode::Left | KeyCode::Right)
+    }
+
+    fn handle_results(&mut self, code: &KeyCode) {
+        match code {
+            code if self.is_back(code) => self.go(Transition::Back),
+            code if self.keys.change_date == *code => self.go(Transition::ChangeDate),
+            code if self.keys.swap == *code => self.swap_stops(),
+            code if self.keys.star == *code => self.toggle_favourite(),
+            code if self.is_switch_focus(code) => self.focus = self.focus.other(),
+            code => {
+                let Some((outward, ret)) = self.expeditions.as_mut() else {
+                    return;
+                };
+                let list = match self.focus {
+                    Direction::Outward => outward,
+                    Direction::Return => ret,
+                };
+                let mut table =
+                    ExpeditionTableComponent::new(list, String::new(), self.decimal_separator);
+                if table.handle_key_event(code) == Action::Confirm {
+                    let expedition = table.selected().cloned();
+                    self.go(Transition::Confirm);
+                    self.details = expedition;
+                    self.details_scroll = 0;
+                }
+            }
+        }
+    }
+
+    fn handle_start(&mut self, code: &KeyCode) {
+        match code {
+            code if self.is_back(code) => self.go(Transition::Back),
+            code if self.is_switch_focus(code) => {
+                self.start_focus = self.start_focus.other();
+                self.fix_start_focus();
+            }
+            code if self.keys.remove == *code => {
+                self.remove_saved_route();
+                self.fix_start_focus();
+            }
+            code => match self.start_focus {
+                StartPanel::Favourites => {
+                    let mut list = RouteListComponent::new(&mut self.favourite_routes, "");
+                    if list.handle_key_event(code) == Action::Confirm {
+                        if let Some(route) = list.selected().cloned() {
+                            self.open_route(&route);
                         }
-                        _ => {}
                     }
-                } else {
-                    match self.expeditions {
-                        Some(ref mut expeditions) => {}
-                        None => {}
+                }
+                StartPanel::Recent => {
+                    let mut list = RouteListComponent::new(&mut self.recent_searches, "");
+                    if list.handle_key_event(code) == Action::Confirm {
+                        if let Some(entry) = list.selected().cloned() {
+                            self.date = entry.date;
+                            self.open_route(&entry.route);
+                        }
                     }
                 }
+            },
+        }
+    }
+
+    // unstars the highlighted favourite or forgets the highlighted search
+    fn remove_saved_route(&mut self) {
+        match self.start_focus {
+            StartPanel::Favourites => {
+                if let Some(route) = self.favourite_routes.selected().cloned() {
+                    self.favourites.toggle(route);
+                    self.save_favourites();
+                }
+            }
+            StartPanel::Recent => {
+                if let Some(entry) = self.recent_searches.selected().cloned() {
+                    self.history.remove(&entry);
+                    self.save_history();
+                }
             }
+        }
+    }
+
+    // keeps the focus off an empty list while the other one has routes
+    fn fix_start_focus(&mut self) {
+        let empty = match self.start_focus {
+            StartPanel::Favourites => self.favourite_routes.items.is_empty(),
+            StartPanel::Recent => self.recent_searches.items.is_empty(),
+        };
+        if empty {
+            self.start_focus = self.start_focus.other();
+            if !self.has_saved_routes() {
+                self.start_focus = StartPanel::default();
+            }
+        }
+    }
+
+    // jumps straight to the results of a known route, keeping the current date
+    fn open_route(&mut self, route: &Route) {
+        let Some((from, to)) = route.resolve(&self.stops) else {
+            return;
+        };
+        self.from_stops.select_item(&from);
+        self.to_stops.select_item(&to);
+        self.desired_stops = (Some(from), Some(to));
+        self.go(Transition::Confirm);
+        self.start_fetch();
+    }
+
+    fn current_route(&self) -> Option<Route> {
+        match &self.desired_stops {
+            (Some(from), Some(to)) => Some(Route::new(from, to)),
+            _ => None,
+        }
+    }
+
+    fn is_favourite(&self) -> bool {
+        self.current_route()
+            .is_some_and(|route| self.favourites.contains(&route))
+    }
+
+    fn toggle_favourite(&mut self) {
+        if let Some(route) = self.current_route() {
+            self.favourites.toggle(route);
+            self.save_favourites();
+        }
+    }
+
+    // writes the favourites file and refreshes the panel, a failed write shows the error
+    fn save_favourites(&mut self) {
+        self.refresh_favourites();
+        let Some(path) = &self.favourites_path else {
+            return;
+        };
+        if let Err(err) = self.favourites.save(path) {
+            self.error = Some(err);
+            self.go(Transition::Fail);
+        }
+    }
+
+    // remembers the search whose expeditions were just loaded
+    fn record_search(&mut self) {
+        if let Some(route) = self.current_route() {
+            self.history
+                .record(HistoryEntry::new(route, self.date), HISTORY_LIMIT);
+            self.save_history();
+        }
+    }
+
+    // writes the history file and refreshes the recent list, a failed write shows the error
+    fn save_history(&mut self) {
+        self.refresh_recent_searches();
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        if let Err(err) = self.history.save(path) {
+            self.error = Some(err);
+            self.go(Transition::Fail);
+        }
+    }
+
+    fn refresh_favourites(&mut self) {
+        self.favourite_routes = served_routes(&self.favourites.routes, &self.stops)
+            .keeping_selection(&self.favourite_routes);
+    }
+
+    fn refresh_recent_searches(&mut self) {
+        self.recent_searches = served_routes(&self.history.entries, &self.stops)
+            .keeping_selection(&self.recent_searches);
+    }
+
+    fn handle_details(&mut self, code: &KeyCode) {
+        match code {
+            code if self.is_back(code) || *code == KeyCode::Enter => self.go(Transition::Back),
+            KeyCode::Up => self.details_scroll = self.details_scroll.saturating_sub(1),
+            KeyCode::Down => self.details_scroll = self.details_scroll.saturating_add(1),
             _ => {}
+        }
+    }
+
+    fn handle_date_selection(&mut self, code: &KeyCode) {
+        if let Some(date) = self.date_picker.handle_events(code) {
+            self.date = date;
+            self.go(Transition::Confirm);
+            self.start_fetch();
+        } else if self.is_back(code) {
+            self.go(Transition::Back);
+        }
+    }
+
+    fn handle_stop_selection(&mut self, code: &KeyCode) {
+        match code {
+            KeyCode::Char(c) => {
+                self.search.push(*c);
+                self.apply_search();
+            }
+            KeyCode::Backspace if !self.search.is_empty() => {
+                self.search.pop();
+                self.apply_search();
+            }
+            KeyCode::Esc if !self.search.is_empty() => self.clear_search(),
+            KeyCode::Esc | KeyCode::Backspace => self.go(Transition::Back),
+            KeyCode::Tab => self.copy_origin_highlight(),
+            code => {
+                let (list, desired) = match self.screen {
+                    Screen::ChooseOrigin => (&mut self.from_stops, &mut self.desired_stops.0),
+                    _ => (&mut self.to_stops, &mut self.desired_stops.1),
+                };
+                let mut component = StopListComponent::new(list, "");
+                if component.handle_key_event(code) == Action::Confirm {
+                    *desired = component.selected().cloned();
+                    self.clear_search();
+                    self.go(Transition::Confirm);
+                }
+            }
+        }
+    }
+
+    // the list the stop search applies to
+    fn searched_list(&mut self) -> &mut StatefulList<Stop> {
+        match self.screen {
+            Screen::ChooseOrigin => &mut self.from_stops,
+            _ => &mut self.to_stops,
+        }
+    }
+
+    // empties the search input, listing every stop again with the same one highlighted
+    fn clear_search(&mut self) {
+        self.search.clear();
+        let stops = self.stops.clone();
+        let list = self.searched_list();
+        let highlighted = list.selected().cloned();
+
+        *list = StatefulList::with_items(stops);
+        if let Some(stop) = highlighted {
+            list.select_item(&stop);
+        }
+    }
+
+    // highlights in the To list the stop highlighted in the From list, for round trips
+    fn copy_origin_highlight(&mut self) {
+        let Some(stop) = self.from_stops.selected().cloned() else {
+            return;
         };
-        Ok(())
+        if self.screen == Screen::ChooseDestination {
+            self.search.clear();
+        }
+        self.to_stops = StatefulList::with_items(self.stops.clone());
+        self.to_stops.select_item(&stop);
+    }
+
+    // turns the trip around and fetches it again for the same date
+    fn swap_stops(&mut self) {
+        let (Some(from), Some(to)) = self.desired_stops.clone() else {
+            return;
+        };
+        self.from_stops.select_item(&to);
+        self.to_stops.select_item(&from);
+        self.desired_stops = (Some(to), Some(from));
+        self.start_fetch();
+    }
+
+    // refilters the list being chosen from, highlighting the best match
+    fn apply_search(&mut self) {
+        let list = StatefulList::with_first_selected(search_stops(&self.stops, &self.search));
+        *self.searched_list() = list;
+    }
+
+    // swaps in a refreshed catalogue, keeping the search and the highlighted stops
+    fn replace_stops(&mut self, stops: Vec<Stop>) {
+        self.stops = stops;
+        let searched = match self.screen {
+            Screen::ChooseOrigin => Some(Screen::ChooseOrigin),
+            Screen::ChooseDestination => Some(Screen::ChooseDestination),
+            _ => None,
+        };
+
+        for screen in [Screen::ChooseOrigin, Screen::ChooseDestination] {
+            let items = if searched == Some(screen) && !self.search.is_empty() {
+                search_stops(&self.stops, &self.search)
+            } else {
+                self.stops.clone()
+            };
+            let list = match screen {
+                Screen::ChooseOrigin => &mut self.from_stops,
+                _ => &mut self.to_stops,
+            };
+            let highlighted = list.selected().map(Stop::get_parada);
+            *list = StatefulList::with_items(items);
+            let position = highlighted
+                .and_then(|id| list.items.iter().position(|stop| stop.get_parada() == id));
+            list.state.select(position);
+        }
+
+        self.refresh_favourites();
+        self.refresh_recent_searches();
+    }
+
+    // how old the cached stops are, for the title bar
+    fn stops_age(&self) -> Option<String> {
+        let fetched_at = self.stops_fetched_at?;
+        let age = format_age(Local::now() - fetched_at);
+        Some(match self.stops_refresh {
+            StopsRefresh::Idle => format!("Stops updated {}", age),
+            StopsRefresh::Running => format!("Stops from {}, refreshing", age),
+            StopsRefresh::Failed => format!("Stops from {}, refresh failed", age),
+        })
     }
 }
 
+/// An age as "just now", "5 min ago", "3 h ago" or "2 days ago"
+fn format_age(age: TimeDelta) -> String {
+    match (age.num_minutes(), age.num_hours(), age.num_days()) {
+        (minutes, _, _) if minutes < 1 => "just now".to_string(),
+        (minutes, hours, _) if hours < 1 => format!("{} min ago", minutes),
+        (_, hours, days) if days < 2 => format!("{} h ago", hours),
+        (_, _, days) => format!("{} days ago", days),
+    }
+}
+
+/// The routes whose stops are all still served
+fn served_routes<T: ListedRoute + Clone>(routes: &[T], stops: &[Stop]) -> StatefulList<T> {
+    let routes = routes
+        .iter()
+        .filter(|item| item.route().resolve(stops).is_some())
+        .cloned()
+        .collect();
+    StatefulList::with_first_selected(routes)
+}
+
+/// Shows a raw JSON field without quotes around strings
+fn format_raw_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "-".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Splits the body into the two side by side columns used by the lists and tables
+fn columns(area: Rect) -> (Rect, Rect) {
+    let chunks = Layout::horizontal([
+        Constraint::Percentage(3),
+        Constraint::Percentage(47),
+        Constraint::Percentage(0),
+        Constraint::Percentage(47),
+        Constraint::Percentage(3),
+    ])
+    .split(area);
+    (chunks[1], chunks[3])
+}
+
+/// Returns a rectangle of the given percentages centered inside `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .split(area);
+
+    Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .split(vertical[1])[1]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use arriva::utils::FixtureTransport;
+    use arriva::ApiConfig;
+
+    fn app() -> App {
+        let stops = vec![
+            Stop::new(1, "A".into(), "A".into(), 1, None, None, None, None),
+            Stop::new(2, "B".into(), "B".into(), 1, None, None, None, None),
+        ];
+        let client =
+            ArrivaClient::with_transport(Backend::Fixture(FixtureTransport), ApiConfig::default());
+        App::new(stops, client)
+    }
+
+    fn app_with_error() -> App {
+        let mut app = app();
+        app.desired_stops = (
+            Some(app.from_stops.items[0].clone()),
+            Some(app.to_stops.items[1].clone()),
+        );
+        app.screen = Screen::Error;
+        app.error = Some(Error::EmptyResults("expeditions"));
+        app
+    }
+
+    #[tokio::test]
+    async fn test_error_popup_retry() {
+        let mut app = app_with_error();
+        app.handle_key_event(KeyCode::Char('r').into());
+
+        assert!(app.error.is_none());
+        assert!(app.is_loading());
+        assert_eq!(app.screen, Screen::Results);
+        assert!(app.desired_stops.1.is_some());
+    }
+
+    #[test]
+    fn test_error_popup_back_to_date() {
+        let mut app = app_with_error();
+        app.handle_key_event(KeyCode::Esc.into());
+
+        assert!(app.error.is_none());
+        assert_eq!(app.screen, Screen::ChooseDate);
+        assert!(app.desired_stops.1.is_some());
+    }
+
+    #[test]
+    fn test_select_without_highlight() {
+        let mut app = app();
+        app.handle_key_event(KeyCode::Enter.into());
+
+        assert_eq!(app.desired_stops, (None, None));
+        assert_eq!(app.screen, Screen::ChooseOrigin);
+    }
+
+    #[tokio::test]
+    async fn test_background_fetch() {
+        let mut app = app_with_error();
+        let mut receiver = app.receiver.take().unwrap();
+        app.handle_key_event(KeyCode::Char('r').into());
+
+        assert!(app.is_loading());
+        assert!(app.error.is_none());
+
+        let message = receiver.recv().await.unwrap();
+        app.handle_message(message);
+
+        assert!(!app.is_loading());
+        assert!(app.expeditions.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_fetch() {
+        let mut app = app_with_error();
+        app.handle_key_event(KeyCode::Char('r').into());
+        app.handle_key_event(KeyCode::Esc.into());
+
+        assert!(!app.is_loading());
+        assert_eq!(app.screen, Screen::ChooseDate);
+        assert!(app.desired_stops.1.is_some());
+    }
+
+    #[test]
+    fn test_ignore_cancelled_results() {
+        let mut app = app_with_error();
+        app.fetch_id = 2;
+        app.handle_message(Message::ExpeditionsLoaded(1, Ok((vec![], vec![]))));
+
+        assert!(app.expeditions.is_none());
+    }
+
+    #[test]
+    fn test_failed_fetch_shows_error() {
+        let mut app = app();
+        app.screen = Screen::Results;
+        app.handle_message(Message::ExpeditionsLoaded(
+            0,
+            Err(Error::EmptyResults("expeditions")),
+        ));
 
-    // #[test]
-    // fn render() {
-    //     let app = App::default();
-    //     let mut buf = Buffer::empty(Rect::new(0, 0, 50, 4));
-
-    //     app.render(buf.area, &mut buf);
-
-    //     let mut expected = Buffer::with_lines(vec![
-    //         "┏━━━━━━━━━━━━━ Counter App Tutorial ━━━━━━━━━━━━━┓",
-    //         "┃                    Value: 0                    ┃",
-    //         "┃                                                ┃",
-    //         "┗━ Decrement <Left> Increment <Right> Quit <Q> ━━┛",
-    //     ]);
-    //     let title_style = Style::new().bold();
-    //     let counter_style = Style::new().yellow();
-    //     let key_style = Style::new().blue().bold();
-    //     expected.set_style(Rect::new(14, 0, 22, 1), title_style);
-    //     expected.set_style(Rect::new(28, 1, 1, 1), counter_style);
-    //     expected.set_style(Rect::new(13, 3, 6, 1), key_style);
-    //     expected.set_style(Rect::new(30, 3, 7, 1), key_style);
-    //     expected.set_style(Rect::new(43, 3, 4, 1), key_style);
-
-    //     // note ratatui also has an assert_buffer_eq! macro that can be used to
-    //     // compare buffers and display the differences in a more readable way
-    //     assert_eq!(buf, expected);
-    // }
-
-    // #[test]
-    // fn handle_key_event() -> Result<()> {
-    //     let mut app = App::default();
-    //     app.handle_key_event(KeyCode::Right.into());
-    //     assert_eq!(app.counter, 1);
-
-    //     app.handle_key_event(KeyCode::Left.into());
-    //     assert_eq!(app.counter, 0);
-
-    //     let mut app = App::default();
-    //     app.handle_key_event(KeyCode::Char('q').into());
-    //     assert_eq!(app.exit, true);
-
-    //     Ok(())
-    // }
+        assert_eq!(app.screen, Screen::Error);
+        assert!(app.error.is_some());
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.handle_key_event(KeyCode::Char(c).into());
+        }
+    }
+
+    #[test]
+    fn test_search_filters_origin() {
+        let mut app = app();
+        type_text(&mut app, "b");
+
+        assert_eq!(app.from_stops.items.len(), 1);
+        assert_eq!(app.to_stops.items.len(), 2);
+
+        app.handle_key_event(KeyCode::Enter.into());
+        assert_eq!(app.desired_stops.0.as_ref().map(Stop::get_parada), Some(2));
+        assert!(app.search.is_empty());
+        // the origin list shows every stop again, still on the chosen one
+        assert_eq!(app.from_stops.items.len(), 2);
+        assert_eq!(app.from_stops.state.selected(), Some(1));
+    }
+
+    #[test]
+    fn test_search_typing_q_does_not_quit() {
+        let mut app = app();
+        type_text(&mut app, "q");
+
+        assert!(!app.exit);
+        assert!(app.from_stops.items.is_empty());
+
+        // navigating an empty list must not panic
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Up.into());
+        app.handle_key_event(KeyCode::Enter.into());
+        assert!(app.desired_stops.0.is_none());
+
+        app.handle_key_event(KeyCode::Esc.into());
+        assert_eq!(app.from_stops.items.len(), 2);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert!(app.exit);
+    }
+
+    #[test]
+    fn test_destination_opens_date_picker() {
+        let mut app = app();
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Enter.into());
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Enter.into());
+
+        assert_eq!(app.screen, Screen::ChooseDate);
+        assert!(!app.is_loading());
+
+        app.handle_key_event(KeyCode::Esc.into());
+        assert_eq!(app.screen, Screen::ChooseDestination);
+        assert!(app.desired_stops.1.is_none());
+    }
+
+    #[test]
+    fn test_back_keeps_list_positions() {
+        let mut app = app();
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Enter.into());
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Enter.into());
+
+        app.handle_key_event(KeyCode::Backspace.into());
+        assert_eq!(app.screen, Screen::ChooseDestination);
+        assert_eq!(app.to_stops.state.selected(), Some(0));
+
+        app.handle_key_event(KeyCode::Esc.into());
+        assert_eq!(app.screen, Screen::ChooseOrigin);
+        assert_eq!(app.desired_stops, (None, None));
+        assert_eq!(app.from_stops.state.selected(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_change_date_and_refetch() {
+        let mut app = app_with_error();
+        app.screen = Screen::Results;
+        app.error = None;
+        app.expeditions = Some((
+            StatefulList::with_items(vec![]),
+            StatefulList::with_items(vec![]),
+        ));
+        let today = app.date;
+
+        app.handle_key_event(KeyCode::Char('d').into());
+        assert_eq!(app.screen, Screen::ChooseDate);
+        app.handle_key_event(KeyCode::Right.into());
+        app.handle_key_event(KeyCode::Enter.into());
+
+        assert_eq!(app.screen, Screen::Results);
+        assert_eq!(app.date, today.succ_opt().unwrap());
+        assert!(app.is_loading());
+    }
+
+    #[tokio::test]
+    async fn test_open_and_close_details() {
+        let mut app = app_with_error();
+        let mut receiver = app.receiver.take().unwrap();
+        app.handle_key_event(KeyCode::Char('r').into());
+        let message = receiver.recv().await.unwrap();
+        app.handle_message(message);
+
+        app.handle_key_event(KeyCode::Tab.into());
+        app.handle_key_event(KeyCode::Enter.into());
+
+        assert_eq!(app.screen, Screen::Details);
+        let details = app.details.as_ref().unwrap();
+        assert_eq!(details.get_raw()["Id_Expedicion"], 20112);
+
+        app.handle_key_event(KeyCode::Esc.into());
+        assert!(app.details.is_none());
+        assert_eq!(app.screen, Screen::Results);
+        assert_eq!(
+            app.expeditions.as_ref().unwrap().1.state.selected(),
+            Some(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_navigate_expedition_tables() {
+        let mut app = app_with_error();
+        let mut receiver = app.receiver.take().unwrap();
+        app.handle_key_event(KeyCode::Char('r').into());
+        let message = receiver.recv().await.unwrap();
+        app.handle_message(message);
+
+        let (outward, ret) = app.expeditions.as_ref().unwrap();
+        assert_eq!(outward.state.selected(), Some(0));
+        assert_eq!(ret.state.selected(), Some(0));
+
+        app.handle_key_event(KeyCode::End.into());
+        app.handle_key_event(KeyCode::Right.into());
+        assert_eq!(app.focus, Direction::Return);
+        app.handle_key_event(KeyCode::PageDown.into());
+
+        let (outward, ret) = app.expeditions.as_ref().unwrap();
+        assert_eq!(outward.state.selected(), Some(outward.items.len() - 1));
+        assert_eq!(ret.state.selected(), Some(ret.items.len() - 1));
+
+        app.handle_key_event(KeyCode::Left.into());
+        app.handle_key_event(KeyCode::Home.into());
+        assert_eq!(app.focus, Direction::Outward);
+        assert_eq!(
+            app.expeditions.as_ref().unwrap().0.state.selected(),
+            Some(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_swap_stops_and_refetch() {
+        let mut app = app_with_error();
+        app.screen = Screen::Results;
+        app.error = None;
+        app.expeditions = Some((
+            StatefulList::with_items(vec![]),
+            StatefulList::with_items(vec![]),
+        ));
+        app.date = app.date.succ_opt().unwrap();
+        let date = app.date;
+
+        app.handle_key_event(KeyCode::Char('s').into());
+
+        let paradas = (
+            app.desired_stops.0.as_ref().map(Stop::get_parada),
+            app.desired_stops.1.as_ref().map(Stop::get_parada),
+        );
+        assert_eq!(paradas, (Some(2), Some(1)));
+        assert_eq!(app.from_stops.state.selected(), Some(1));
+        assert_eq!(app.to_stops.state.selected(), Some(0));
+        assert_eq!(app.date, date);
+        assert!(app.is_loading());
+        assert_eq!(app.screen, Screen::Results);
+    }
+
+    #[test]
+    fn test_copy_origin_highlight() {
+        let mut app = app();
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Down.into());
+        app.handle_key_event(KeyCode::Tab.into());
+        assert_eq!(app.to_stops.state.selected(), Some(1));
+
+        app.handle_key_event(KeyCode::Enter.into());
+        type_text(&mut app, "a");
+        app.handle_key_event(KeyCode::Tab.into());
+
+        assert!(app.search.is_empty());
+        assert_eq!(app.to_stops.items.len(), 2);
+        assert_eq!(app.to_stops.selected().map(Stop::get_parada), Some(2));
+    }
+
+    fn favourite() -> Favourites {
+        Favourites {
+            routes: vec![
+                Route {
+                    from: 2,
+                    from_name: "B".into(),
+                    to: 1,
+                    to_name: "A".into(),
+                },
+                // not served anymore, so never listed
+                Route {
+                    from: 2,
+                    from_name: "B".into(),
+                    to: 9,
+                    to_name: "Z".into(),
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_favourite() {
+        let mut app = app().with_favourites(favourite(), None);
+        assert_eq!(app.screen, Screen::Start);
+        assert_eq!(app.favourite_routes.items.len(), 1);
+
+        app.handle_key_event(KeyCode::Enter.into());
+
+        assert_eq!(app.screen, Screen::Results);
+        assert!(app.is_loading());
+        let paradas = (
+            app.desired_stops.0.as_ref().map(Stop::get_parada),
+            app.desired_stops.1.as_ref().map(Stop::get_parada),
+        );
+        assert_eq!(paradas, (Some(2), Some(1)));
+        assert_eq!(app.to_stops.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_favourites_panel_back_and_forth() {
+        let mut app = app().with_favourites(favourite(), None);
+        app.handle_key_event(KeyCode::Esc.into());
+        assert_eq!(app.screen, Screen::ChooseOrigin);
+
+        app.handle_key_event(KeyCode::Esc.into());
+        assert_eq!(app.screen, Screen::Start);
+
+        app.handle_key_event(KeyCode::Delete.into());
+        assert!(app.favourite_routes.items.is_empty());
+        assert_eq!(app.favourites.routes.len(), 1);
+    }
+
+    #[test]
+    fn test_star_route_from_results() {
+        let mut app = app_with_error();
+        app.screen = Screen::Results;
+        app.error = None;
+        app.expeditions = Some((
+            StatefulList::with_items(vec![]),
+            StatefulList::with_items(vec![]),
+        ));
+
+        app.handle_key_event(KeyCode::Char('f').into());
+        assert!(app.is_favourite());
+        assert_eq!(app.favourite_routes.items.len(), 1);
+
+        app.handle_key_event(KeyCode::Char('f').into());
+        assert!(!app.is_favourite());
+        assert!(app.favourite_routes.items.is_empty());
+    }
+
+    fn history() -> History {
+        let route = Route {
+            from: 1,
+            from_name: "A".into(),
+            to: 2,
+            to_name: "B".into(),
+        };
+        History {
+            entries: vec![HistoryEntry::new(
+                route,
+                NaiveDate::from_ymd_opt(2024, 5, 21).unwrap(),
+            )],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerun_recent_search() {
+        let mut app = app()
+            .with_favourites(favourite(), None)
+            .with_history(history(), None);
+        assert_eq!(app.screen, Screen::Start);
+        assert_eq!(app.start_focus, StartPanel::Favourites);
+
+        app.handle_key_event(KeyCode::Tab.into());
+        app.handle_key_event(KeyCode::Enter.into());
+
+        assert_eq!(app.screen, Screen::Results);
+        assert_eq!(app.date, NaiveDate::from_ymd_opt(2024, 5, 21).unwrap());
+        assert_eq!(app.desired_stops.0.as_ref().map(Stop::get_parada), Some(1));
+    }
+
+    #[test]
+    fn test_remove_recent_search() {
+        let mut app = app().with_history(history(), None);
+        assert_eq!(app.screen, Screen::Start);
+        assert_eq!(app.start_focus, StartPanel::Recent);
+
+        app.handle_key_event(KeyCode::Delete.into());
+        assert!(app.recent_searches.items.is_empty());
+        assert!(app.history.entries.is_empty());
+
+        // nothing left to go back to
+        app.handle_key_event(KeyCode::Esc.into());
+        app.handle_key_event(KeyCode::Esc.into());
+        assert_eq!(app.screen, Screen::ChooseOrigin);
+    }
+
+    #[tokio::test]
+    async fn test_record_loaded_search() {
+        let mut app = app_with_error();
+        app.handle_key_event(KeyCode::Char('r').into());
+        let message = app.receiver.as_mut().unwrap().recv().await.unwrap();
+        app.handle_message(message);
+
+        assert_eq!(app.history.entries.len(), 1);
+        assert_eq!(app.recent_searches.items[0].route.from, 1);
+        assert_eq!(app.recent_searches.items[0].date, app.date);
+    }
+
+    #[test]
+    fn test_with_config() {
+        let mut config = Config::default();
+        config.defaults.origin = Some(2);
+        config.defaults.date_offset = 1;
+        config.keys.quit = "x".parse().unwrap();
+        let mut app = app().with_config(&config).unwrap();
+
+        assert_eq!(app.from_stops.selected().map(Stop::get_parada), Some(2));
+        assert_eq!(app.date, Local::now().date_naive().succ_opt().unwrap());
+
+        app.screen = Screen::ChooseDate;
+        app.handle_key_event(KeyCode::Char('q').into());
+        assert!(!app.exit);
+        app.handle_key_event(KeyCode::Char('x').into());
+        assert!(app.exit);
+
+        config.defaults.origin = Some(9);
+        assert!(matches!(
+            self::app().with_config(&config),
+            Err(Error::UnknownStop(stop)) if stop == "9"
+        ));
+    }
+
+    #[test]
+    fn test_refreshed_stops_keep_search_and_highlight() {
+        let mut app = app();
+        type_text(&mut app, "b");
+        app.to_stops.state.select(Some(0));
+        let stops = vec![
+            Stop::new(1, "A".into(), "A".into(), 1, None, None, None, None),
+            Stop::new(3, "C".into(), "C".into(), 1, None, None, None, None),
+            Stop::new(2, "Bus".into(), "B".into(), 1, None, None, None, None),
+        ];
+        app.stops_refresh = StopsRefresh::Running;
+        app.handle_message(Message::StopsRefreshed(Ok(StopCache::new(
+            stops,
+            Default::default(),
+        ))));
+
+        assert_eq!(app.stops_refresh, StopsRefresh::Idle);
+        assert!(app.stops_fetched_at.is_some());
+        assert_eq!(app.from_stops.items.len(), 1);
+        assert_eq!(app.from_stops.selected().map(Stop::get_parada), Some(2));
+        assert_eq!(app.to_stops.items.len(), 3);
+        assert_eq!(app.to_stops.selected().map(Stop::get_parada), Some(1));
+
+        app.handle_message(Message::StopsRefreshed(Err(Error::EmptyResults("stops"))));
+        assert_eq!(app.stops_refresh, StopsRefresh::Failed);
+        assert_eq!(app.to_stops.items.len(), 3);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(TimeDelta::seconds(30)), "just now");
+        assert_eq!(format_age(TimeDelta::minutes(5)), "5 min ago");
+        assert_eq!(format_age(TimeDelta::hours(3)), "3 h ago");
+        assert_eq!(format_age(TimeDelta::hours(47)), "47 h ago");
+        assert_eq!(format_age(TimeDelta::days(3)), "3 days ago");
+    }
 }
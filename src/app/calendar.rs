@@ -0,0 +1,144 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use crossterm::event::KeyCode;
+use ratatui::{prelude::*, widgets::*};
+
+use crate::prelude::*;
+
+const WEEKDAYS: &str = "Mo Tu We Th Fr Sa Su";
+
+/// Month view to pick the travel date, moving by day, week or month
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatePicker {
+    pub date: NaiveDate,
+    today: NaiveDate,
+}
+
+impl Default for DatePicker {
+    fn default() -> Self {
+        Self::new(Local::now().date_naive())
+    }
+}
+
+impl DatePicker {
+    pub fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            today: Local::now().date_naive(),
+        }
+    }
+
+    pub fn handle_events(&mut self, code: &KeyCode) -> Option<NaiveDate> {
+        let moved = match code {
+            KeyCode::Left => self.date.checked_sub_days(Days::new(1)),
+            KeyCode::Right => self.date.checked_add_days(Days::new(1)),
+            KeyCode::Up => self.date.checked_sub_days(Days::new(7)),
+            KeyCode::Down => self.date.checked_add_days(Days::new(7)),
+            KeyCode::PageUp => self.date.checked_sub_months(Months::new(1)),
+            KeyCode::PageDown => self.date.checked_add_months(Months::new(1)),
+            KeyCode::Char('t') => Some(self.today),
+            KeyCode::Enter => return Some(self.date),
+            _ => None,
+        };
+
+        if let Some(date) = moved {
+            self.date = date;
+        }
+        None
+    }
+
+    /// One line per week of the selected month, with the selected day highlighted
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let first = self.date.with_day(1).unwrap_or(self.date);
+        let days_in_month = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .map_or(31, |last| last.day());
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                first.format("%B %Y").to_string(),
+                theme().title,
+            )),
+            Line::from(""),
+            Line::from(Span::styled(WEEKDAYS, theme().muted)),
+        ];
+
+        // every day takes two spans, the number and its separator
+        let mut week: Vec<Span> = (0..first.weekday().num_days_from_monday())
+            .flat_map(|_| [Span::raw("  "), Span::raw(" ")])
+            .collect();
+        for day in 1..=days_in_month {
+            let mut span = Span::raw(format!("{:>2}", day));
+            if day == self.date.day() {
+                span = span.reversed().bold();
+            } else if first.with_day(day) == Some(self.today) {
+                span = span.underlined();
+            }
+            week.push(span);
+            week.push(Span::raw(" "));
+
+            if week.len() == 14 {
+                lines.push(Line::from(std::mem::take(&mut week)));
+            }
+        }
+        if !week.is_empty() {
+            lines.push(Line::from(week));
+        }
+
+        lines
+    }
+
+    pub fn widget(&self) -> Paragraph<'static> {
+        Paragraph::new(self.lines()).alignment(Alignment::Center)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_move_by_day_and_week() {
+        let mut picker = DatePicker::new(date(2024, 5, 31));
+        picker.handle_events(&KeyCode::Right);
+        assert_eq!(picker.date, date(2024, 6, 1));
+
+        picker.handle_events(&KeyCode::Up);
+        assert_eq!(picker.date, date(2024, 5, 25));
+
+        picker.handle_events(&KeyCode::Down);
+        picker.handle_events(&KeyCode::Left);
+        assert_eq!(picker.date, date(2024, 5, 31));
+    }
+
+    #[test]
+    fn test_move_by_month_clamps_day() {
+        let mut picker = DatePicker::new(date(2024, 1, 31));
+        picker.handle_events(&KeyCode::PageDown);
+        assert_eq!(picker.date, date(2024, 2, 29));
+    }
+
+    #[test]
+    fn test_confirm_returns_date() {
+        let mut picker = DatePicker::new(date(2024, 5, 20));
+        assert_eq!(picker.handle_events(&KeyCode::Down), None);
+        assert_eq!(
+            picker.handle_events(&KeyCode::Enter),
+            Some(date(2024, 5, 27))
+        );
+    }
+
+    #[test]
+    fn test_month_grid() {
+        // May 2024 starts on a Wednesday and has 31 days
+        let lines = DatePicker::new(date(2024, 5, 20)).lines();
+        let first_week: String = lines[3].spans.iter().map(|s| s.content.as_ref()).collect();
+
+        assert_eq!(first_week.trim_end(), "       1  2  3  4  5");
+        assert_eq!(lines.len(), 3 + 5);
+    }
+}
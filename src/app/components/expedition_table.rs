@@ -0,0 +1,109 @@
+use crossterm::event::KeyCode;
+use ratatui::{prelude::*, symbols::border, widgets::*};
+
+use crate::app::StatefulList;
+use crate::prelude::*;
+
+use super::{Action, Component};
+
+const HEADER: [&str; 5] = ["LINEA", "SALIDA", "CHEGADA", "DURACIÓN", "COSTE(€)"];
+// the times, duration and fare as wide as their header or longest value ("00:25 (+1)"),
+// the line name takes what is left
+const WIDTHS: [Constraint; 5] = [
+    Constraint::Min(0),
+    Constraint::Length(6),
+    Constraint::Length(10),
+    Constraint::Length(8),
+    Constraint::Length(8),
+];
+
+/// The expeditions of one direction, drawn with a thick border when it has the focus
+pub struct ExpeditionTableComponent<'a> {
+    list: &'a mut StatefulList<Expedition>,
+    title: String,
+    focused: bool,
+    decimal_separator: DecimalSeparator,
+}
+
+impl<'a> ExpeditionTableComponent<'a> {
+    pub fn new(
+        list: &'a mut StatefulList<Expedition>,
+        title: String,
+        decimal_separator: DecimalSeparator,
+    ) -> Self {
+        Self {
+            list,
+            title,
+            focused: false,
+            decimal_separator,
+        }
+    }
+
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    pub fn selected(&self) -> Option<&Expedition> {
+        self.list.selected()
+    }
+
+    fn row(&self, expedition: &Expedition) -> Row<'static> {
+        let theme = theme();
+
+        Row::new(vec![
+            Cell::from(expedition.get_name()).style(theme.text.add_modifier(Modifier::BOLD)),
+            Cell::from(expedition.get_departure().format("%H:%M").to_string()).style(theme.time),
+            Cell::from(format_arrival(expedition)).style(theme.time),
+            Cell::from(format_duration(expedition.get_duration())).style(theme.time),
+            Cell::from(
+                expedition
+                    .get_fare()
+                    .basic
+                    .format_amount(self.decimal_separator),
+            )
+            .style(theme.fare),
+        ])
+    }
+}
+
+impl Component for ExpeditionTableComponent<'_> {
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self.list.items.iter().map(|i| self.row(i)).collect();
+
+        let block = if self.focused {
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(border::THICK)
+                .border_style(theme().highlight)
+        } else {
+            Block::default().borders(Borders::ALL)
+        };
+
+        let table = Table::new(rows, WIDTHS)
+            .block(block.title(Span::styled(self.title.clone(), theme().title)))
+            .header(Row::new(HEADER).style(theme().header))
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::REVERSED),
+            )
+            .highlight_symbol(if self.focused { "->  " } else { "    " });
+
+        // the table keeps its own state, synced with the list so the scroll survives frames
+        let mut state = TableState::default()
+            .with_selected(self.list.state.selected())
+            .with_offset(self.list.state.offset());
+        // borders and header take three rows
+        self.list.page_size = area.height.saturating_sub(3) as usize;
+        frame.render_stateful_widget(table, area, &mut state);
+        *self.list.state.offset_mut() = state.offset();
+    }
+
+    fn handle_key_event(&mut self, code: &KeyCode) -> Action {
+        match self.list.handle_events(code) {
+            Some(_) => Action::Confirm,
+            None => Action::None,
+        }
+    }
+}
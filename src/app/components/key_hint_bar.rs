@@ -0,0 +1,78 @@
+use ratatui::{
+    prelude::*,
+    widgets::{block::*, *},
+};
+
+use crate::config::Key;
+use crate::prelude::*;
+
+use super::Component;
+
+/// A row of " Label <Key>" hints, used at the bottom of the screen and of popups
+#[derive(Debug, Default, Clone)]
+pub struct KeyHintBar {
+    hints: Vec<(&'static str, String)>,
+}
+
+impl KeyHintBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hint(mut self, label: &'static str, key: impl Into<String>) -> Self {
+        self.hints.push((label, key.into()));
+        self
+    }
+
+    /// A hint for a configurable key
+    pub fn key(self, label: &'static str, key: Key) -> Self {
+        self.hint(label, format!("<{}>", key))
+    }
+
+    pub fn line(&self) -> Line<'static> {
+        let mut spans: Vec<Span> = self
+            .hints
+            .iter()
+            .flat_map(|(label, key)| {
+                [
+                    Span::raw(format!(" {} ", label)),
+                    Span::styled(key.clone(), theme().highlight),
+                ]
+            })
+            .collect();
+        spans.push(Span::raw(" "));
+        Line::from(spans)
+    }
+
+    /// The hints as a block title, centered at the bottom of the block
+    pub fn title(&self) -> Title<'static> {
+        Title::from(self.line())
+            .alignment(Alignment::Center)
+            .position(Position::Bottom)
+    }
+}
+
+impl Component for KeyHintBar {
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Block::default().borders(Borders::NONE).title(self.title()),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint_line() {
+        let line = KeyHintBar::new()
+            .hint("Quit", "<Q>")
+            .hint("Back", "<Esc>")
+            .line();
+        let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+
+        assert_eq!(text, " Quit <Q> Back <Esc> ");
+    }
+}
@@ -0,0 +1,32 @@
+mod expedition_table;
+mod key_hint_bar;
+mod route_list;
+mod stop_list;
+mod title_bar;
+
+pub use expedition_table::ExpeditionTableComponent;
+pub use key_hint_bar::KeyHintBar;
+pub use route_list::{ListedRoute, RouteListComponent};
+pub use stop_list::StopListComponent;
+pub use title_bar::TitleBar;
+
+use crossterm::event::KeyCode;
+use ratatui::{layout::Rect, Frame};
+
+/// What a component asks the app to do once it has handled a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// The component dealt with the key itself, or ignored it
+    None,
+    /// The highlighted item was chosen
+    Confirm,
+}
+
+/// A piece of the interface that draws itself into an area and reacts to keys
+pub trait Component {
+    fn render(&mut self, frame: &mut Frame, area: Rect);
+
+    fn handle_key_event(&mut self, _code: &KeyCode) -> Action {
+        Action::None
+    }
+}
@@ -0,0 +1,109 @@
+use crossterm::event::KeyCode;
+use ratatui::{prelude::*, symbols::border, widgets::*};
+
+use crate::app::StatefulList;
+use crate::prelude::*;
+
+use super::{Action, Component};
+
+/// An item of a `RouteListComponent`
+pub trait ListedRoute {
+    fn route(&self) -> &Route;
+
+    /// Shown before the stops, so it is not cut off with long stop names
+    fn note(&self) -> Option<String> {
+        None
+    }
+}
+
+impl ListedRoute for Route {
+    fn route(&self) -> &Route {
+        self
+    }
+}
+
+impl ListedRoute for HistoryEntry {
+    fn route(&self) -> &Route {
+        &self.route
+    }
+
+    fn note(&self) -> Option<String> {
+        Some(self.date.format(DATE_FORMAT).to_string())
+    }
+}
+
+/// A framed list of From -> To routes, drawn with a thick border when it has the focus
+pub struct RouteListComponent<'a, T> {
+    list: &'a mut StatefulList<T>,
+    title: &'static str,
+    focused: bool,
+}
+
+impl<'a, T: ListedRoute + Clone> RouteListComponent<'a, T> {
+    pub fn new(list: &'a mut StatefulList<T>, title: &'static str) -> Self {
+        Self {
+            list,
+            title,
+            focused: true,
+        }
+    }
+
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.list.selected()
+    }
+}
+
+impl<T: ListedRoute + Clone> Component for RouteListComponent<'_, T> {
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .list
+            .items
+            .iter()
+            .map(|item| {
+                let route = item.route();
+                let note = item.note().map(|note| format!("{} · ", note));
+                ListItem::new(Line::from(vec![
+                    Span::styled(note.unwrap_or_default(), theme().muted),
+                    Span::styled(route.from_name.clone(), theme().text),
+                    Span::styled(" -> ", theme().muted),
+                    Span::styled(route.to_name.clone(), theme().text),
+                ]))
+            })
+            .collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled(self.title, theme().title));
+        let block = if self.focused {
+            block
+                .border_set(border::THICK)
+                .border_style(theme().highlight)
+        } else {
+            block
+        };
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::ITALIC),
+            )
+            .highlight_symbol(if self.focused { "->  " } else { "    " });
+
+        self.list.page_size = area.height.saturating_sub(2) as usize;
+        frame.render_stateful_widget(list, area, &mut self.list.state);
+    }
+
+    fn handle_key_event(&mut self, code: &KeyCode) -> Action {
+        match self.list.handle_events(code) {
+            Some(_) => Action::Confirm,
+            None => Action::None,
+        }
+    }
+}
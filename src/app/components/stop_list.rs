@@ -0,0 +1,78 @@
+use crossterm::event::KeyCode;
+use ratatui::{prelude::*, widgets::*};
+
+use crate::app::StatefulList;
+use crate::prelude::*;
+
+use super::{Action, Component};
+
+/// One of the two stop lists, framed while it is the one being chosen from
+pub struct StopListComponent<'a> {
+    list: &'a mut StatefulList<Stop>,
+    title: &'static str,
+    focused: bool,
+}
+
+impl<'a> StopListComponent<'a> {
+    pub fn new(list: &'a mut StatefulList<Stop>, title: &'static str) -> Self {
+        Self {
+            list,
+            title,
+            focused: false,
+        }
+    }
+
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    pub fn selected(&self) -> Option<&Stop> {
+        self.list.selected()
+    }
+}
+
+impl Component for StopListComponent<'_> {
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .list
+            .items
+            .iter()
+            .map(|i| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(i.get_parada().to_string(), theme().muted),
+                    Span::raw(" - "),
+                    Span::styled(i.get_nombre(), theme().text),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(if self.focused {
+                        Borders::ALL
+                    } else {
+                        Borders::NONE
+                    })
+                    .title(self.title),
+            )
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::ITALIC),
+            )
+            .highlight_symbol("->  ");
+
+        // borders take two rows
+        self.list.page_size = area.height.saturating_sub(2) as usize;
+        frame.render_stateful_widget(list, area, &mut self.list.state);
+    }
+
+    fn handle_key_event(&mut self, code: &KeyCode) -> Action {
+        match self.list.handle_events(code) {
+            Some(_) => Action::Confirm,
+            None => Action::None,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use ratatui::{
+    prelude::*,
+    widgets::{block::*, *},
+};
+
+use crate::prelude::*;
+
+use super::Component;
+
+/// The application name, centered on top of every screen, with an optional status
+/// on the right
+pub struct TitleBar {
+    title: &'static str,
+    status: Option<String>,
+}
+
+impl TitleBar {
+    pub fn new(title: &'static str) -> Self {
+        Self {
+            title,
+            status: None,
+        }
+    }
+
+    pub fn status(mut self, status: Option<String>) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+impl Component for TitleBar {
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        let title = Title::from(Span::styled(self.title, theme().title))
+            .alignment(Alignment::Center)
+            .position(Position::Top);
+
+        let mut block = Block::default().borders(Borders::NONE).title(title);
+        if let Some(status) = &self.status {
+            block = block.title(
+                Title::from(Span::styled(format!("{} ", status), theme().muted))
+                    .alignment(Alignment::Right)
+                    .position(Position::Top),
+            );
+        }
+        frame.render_widget(block, area);
+    }
+}
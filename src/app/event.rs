@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use crate::prelude::*;
+
+use crossterm::event::{Event as CrosstermEvent, EventStream, KeyEvent};
+use futures::StreamExt;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{self, Interval},
+};
+
+/// Results posted back to the app by background tasks
+#[derive(Debug)]
+pub enum Message {
+    /// Outcome of the fetch identified by the first field
+    ExpeditionsLoaded(usize, Result<(Vec<Expedition>, Vec<Expedition>)>),
+    /// Outcome of the background refresh of the cached stops
+    StopsRefreshed(Result<StopCache>),
+}
+
+/// Everything the main loop can react to
+#[derive(Debug)]
+pub enum Event {
+    Key(KeyEvent),
+    Resize,
+    Tick,
+    Message(Message),
+}
+
+pub fn channel() -> (UnboundedSender<Message>, UnboundedReceiver<Message>) {
+    mpsc::unbounded_channel()
+}
+
+/// Merges terminal input, a periodic tick and background task messages into one stream
+pub struct EventHandler {
+    events: EventStream,
+    tick: Interval,
+    messages: UnboundedReceiver<Message>,
+}
+
+impl EventHandler {
+    pub fn new(messages: UnboundedReceiver<Message>, tick_rate: Duration) -> Self {
+        Self {
+            events: EventStream::new(),
+            tick: time::interval(tick_rate),
+            messages,
+        }
+    }
+
+    pub async fn next(&mut self) -> Result<Event> {
+        loop {
+            let event = tokio::select! {
+                event = self.events.next() => match event {
+                    Some(Ok(CrosstermEvent::Key(key))) => Event::Key(key),
+                    Some(Ok(CrosstermEvent::Resize(_, _))) => Event::Resize,
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Err(Error::Generic("Terminal event stream closed".to_string())),
+                },
+                _ = self.tick.tick() => Event::Tick,
+                Some(message) = self.messages.recv() => Event::Message(message),
+            };
+            return Ok(event);
+        }
+    }
+}
@@ -1,6 +1,13 @@
+#[allow(clippy::module_inception)]
 pub mod app;
+pub mod calendar;
+pub mod components;
+pub mod event;
+pub mod screen;
 pub mod stateful_list;
 pub mod tui;
+#[cfg(test)]
+mod ui_tests;
 
 pub use app::App;
 pub use stateful_list::StatefulList;
@@ -0,0 +1,153 @@
+/// Every screen of the app, in the order they are walked through
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// The starred routes and the recent searches, shown on startup when there are any
+    Start,
+    #[default]
+    ChooseOrigin,
+    ChooseDestination,
+    ChooseDate,
+    /// The expedition tables, or a spinner while they are being fetched
+    Results,
+    Details,
+    Error,
+}
+
+/// What the user (or a background fetch) asked for on the current screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Chose the highlighted route, stop, date or expedition
+    Confirm,
+    Back,
+    ChangeDate,
+    Retry,
+    Fail,
+}
+
+/// What some transitions depend on besides the current screen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    /// There are expeditions to go back to when leaving the date selection
+    pub has_results: bool,
+    /// There are starred routes or recent searches to go back to when leaving the origin
+    /// selection
+    pub has_saved_routes: bool,
+}
+
+impl Screen {
+    /// Screen reached from `self` through `transition`
+    pub fn next(self, transition: Transition, context: Context) -> Screen {
+        match (self, transition) {
+            (_, Transition::Fail) => Screen::Error,
+            // a starred route or a recent search already knows both stops
+            (Screen::Start, Transition::Confirm) => Screen::Results,
+            (Screen::Start, Transition::Back) => Screen::ChooseOrigin,
+            (Screen::ChooseOrigin, Transition::Confirm) => Screen::ChooseDestination,
+            (Screen::ChooseDestination, Transition::Confirm) => Screen::ChooseDate,
+            (Screen::ChooseDate, Transition::Confirm) => Screen::Results,
+            (Screen::Results, Transition::Confirm) => Screen::Details,
+            (Screen::Results, Transition::ChangeDate) => Screen::ChooseDate,
+            (Screen::Error, Transition::Retry) => Screen::Results,
+            (Screen::ChooseOrigin, Transition::Back) if context.has_saved_routes => Screen::Start,
+            (Screen::ChooseDestination, Transition::Back) => Screen::ChooseOrigin,
+            (Screen::ChooseDate, Transition::Back) if context.has_results => Screen::Results,
+            (Screen::ChooseDate, Transition::Back) => Screen::ChooseDestination,
+            (Screen::Results | Screen::Error, Transition::Back) => Screen::ChooseDate,
+            (Screen::Details, Transition::Back) => Screen::Results,
+            (screen, _) => screen,
+        }
+    }
+
+    /// Whether keys go to the stop search input
+    pub fn is_choosing_stops(self) -> bool {
+        matches!(self, Screen::ChooseOrigin | Screen::ChooseDestination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTHING: Context = Context {
+        has_results: false,
+        has_saved_routes: false,
+    };
+    const RESULTS: Context = Context {
+        has_results: true,
+        has_saved_routes: false,
+    };
+
+    #[test]
+    fn test_forward_path() {
+        let mut screen = Screen::default();
+        for expected in [
+            Screen::ChooseDestination,
+            Screen::ChooseDate,
+            Screen::Results,
+            Screen::Details,
+        ] {
+            screen = screen.next(Transition::Confirm, NOTHING);
+            assert_eq!(screen, expected);
+        }
+    }
+
+    #[test]
+    fn test_back_goes_one_step() {
+        assert_eq!(
+            Screen::Details.next(Transition::Back, RESULTS),
+            Screen::Results
+        );
+        assert_eq!(
+            Screen::Results.next(Transition::Back, RESULTS),
+            Screen::ChooseDate
+        );
+        assert_eq!(
+            Screen::ChooseDate.next(Transition::Back, NOTHING),
+            Screen::ChooseDestination
+        );
+        assert_eq!(
+            Screen::ChooseDestination.next(Transition::Back, NOTHING),
+            Screen::ChooseOrigin
+        );
+        assert_eq!(
+            Screen::ChooseOrigin.next(Transition::Back, NOTHING),
+            Screen::ChooseOrigin
+        );
+    }
+
+    #[test]
+    fn test_change_date_returns_to_results() {
+        let screen = Screen::Results.next(Transition::ChangeDate, RESULTS);
+        assert_eq!(screen, Screen::ChooseDate);
+        assert_eq!(screen.next(Transition::Back, RESULTS), Screen::Results);
+    }
+
+    #[test]
+    fn test_error_retry_and_back() {
+        let screen = Screen::Results.next(Transition::Fail, NOTHING);
+        assert_eq!(screen, Screen::Error);
+        assert_eq!(screen.next(Transition::Retry, NOTHING), Screen::Results);
+        assert_eq!(screen.next(Transition::Back, NOTHING), Screen::ChooseDate);
+        assert_eq!(screen.next(Transition::Confirm, NOTHING), Screen::Error);
+    }
+
+    #[test]
+    fn test_start_screen() {
+        let saved = Context {
+            has_results: false,
+            has_saved_routes: true,
+        };
+        assert_eq!(
+            Screen::Start.next(Transition::Confirm, saved),
+            Screen::Results
+        );
+        assert_eq!(
+            Screen::Start.next(Transition::Back, saved),
+            Screen::ChooseOrigin
+        );
+        assert_eq!(
+            Screen::ChooseOrigin.next(Transition::Back, saved),
+            Screen::Start
+        );
+    }
+}
@@ -1,39 +1,37 @@
-use crate::prelude::*;
-use crossterm::event::{self, KeyCode};
+use crossterm::event::KeyCode;
 use ratatui::widgets::ListState;
 
-use crate::App;
+// rows moved by PageUp/PageDown until the list is rendered and knows its height
+const DEFAULT_PAGE_SIZE: usize = 10;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
+    pub page_size: usize,
 }
 
-impl Default for StatefulList<Stop> {
+impl<T> Default for StatefulList<T> {
     fn default() -> Self {
         Self {
             state: ListState::default(),
             items: Vec::new(),
+            page_size: DEFAULT_PAGE_SIZE,
         }
     }
 }
 
-impl StatefulList<Stop> {
-    pub fn draw_stop_list() {}
-
-    pub fn handle_events(&mut self, code: &KeyCode) -> Option<Stop> {
+impl<T: Clone> StatefulList<T> {
+    /// Moves the highlight, returning the highlighted item when it is confirmed with Enter
+    pub fn handle_events(&mut self, code: &KeyCode) -> Option<T> {
         match code {
             KeyCode::Up => self.previous(),
             KeyCode::Down => self.next(),
-            KeyCode::Enter => {
-                return Some(
-                    self.items
-                        .get(self.state.selected().unwrap())
-                        .unwrap()
-                        .clone(),
-                )
-            }
+            KeyCode::PageUp => self.page_up(),
+            KeyCode::PageDown => self.page_down(),
+            KeyCode::Home => self.first(),
+            KeyCode::End => self.last(),
+            KeyCode::Enter => return self.selected().cloned(),
             _ => {}
         };
 
@@ -41,29 +39,82 @@ impl StatefulList<Stop> {
     }
 }
 
-impl StatefulList<Expedition> {
-    pub fn draw_stop_list() {}
-
-    pub fn handle_events(&mut self, code: &KeyCode) -> Option<Expedition> {
-        match code {
-            KeyCode::Up => self.previous(),
-            KeyCode::Down => self.next(),
-            _ => {}
-        }
-
-        None
-    }
-}
-
 impl<T> StatefulList<T> {
     pub fn with_items(items: Vec<T>) -> Self {
         Self {
             state: ListState::default(),
             items,
+            page_size: DEFAULT_PAGE_SIZE,
         }
     }
 
+    /// Same as `with_items` but highlighting the first item, if any
+    pub fn with_first_selected(items: Vec<T>) -> Self {
+        let mut list = Self::with_items(items);
+        list.first();
+        list
+    }
+
+    /// Highlights the same position `previous` had, or the last item if the list got shorter
+    pub fn keeping_selection(mut self, previous: &StatefulList<T>) -> Self {
+        if let Some(i) = previous.state.selected() {
+            let last = self.items.len().checked_sub(1);
+            self.state.select(last.map(|last| i.min(last)));
+        }
+        self
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.state.selected().and_then(|i| self.items.get(i))
+    }
+
+    /// Highlights `item`, or nothing when it is not in the list
+    pub fn select_item(&mut self, item: &T)
+    where
+        T: PartialEq,
+    {
+        let position = self.items.iter().position(|i| i == item);
+        self.state.select(position);
+    }
+
+    pub fn first(&mut self) {
+        if !self.items.is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    pub fn last(&mut self) {
+        if !self.items.is_empty() {
+            self.state.select(Some(self.items.len() - 1));
+        }
+    }
+
+    pub fn page_down(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => (i + self.page_size.max(1)).min(self.items.len() - 1),
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn page_up(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.state.selected() {
+            Some(i) => i.saturating_sub(self.page_size.max(1)),
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -78,6 +129,9 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -91,3 +145,37 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paging() {
+        let mut list = StatefulList::with_items((0..25).collect::<Vec<usize>>());
+        list.page_size = 10;
+
+        list.page_down();
+        assert_eq!(list.state.selected(), Some(0));
+        list.page_down();
+        assert_eq!(list.state.selected(), Some(10));
+        list.page_down();
+        list.page_down();
+        assert_eq!(list.state.selected(), Some(24));
+        list.page_up();
+        assert_eq!(list.state.selected(), Some(14));
+        list.first();
+        assert_eq!(list.state.selected(), Some(0));
+        list.last();
+        assert_eq!(list.state.selected(), Some(24));
+    }
+
+    #[test]
+    fn test_empty_list_navigation() {
+        let mut list: StatefulList<usize> = StatefulList::with_first_selected(vec![]);
+        list.page_down();
+        list.last();
+        list.next();
+        assert_eq!(list.state.selected(), None);
+    }
+}
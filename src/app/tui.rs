@@ -1,10 +1,7 @@
 use std::io::{self, stdout, Stdout};
 
 use crate::prelude::*;
-use crossterm::{
-    execute,
-    terminal::{self, *},
-};
+use crossterm::{execute, terminal::*};
 use ratatui::prelude::*;
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
@@ -13,7 +10,7 @@ pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 pub fn init() -> Result<Tui> {
     execute!(stdout(), EnterAlternateScreen)?;
     enable_raw_mode()?;
-    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     Ok(terminal)
 }
 
@@ -23,3 +20,13 @@ pub fn restore() -> io::Result<()> {
     disable_raw_mode()?;
     Ok(())
 }
+
+/// Restore the terminal before printing the panic report, so a panic never
+/// leaves the user stuck in raw mode on the alternate screen
+pub fn install_panic_hook() {
+    let original_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        let _ = restore();
+        original_hook(panic_info);
+    }));
+}
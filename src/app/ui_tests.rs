@@ -0,0 +1,214 @@
+//! Renders `App` into a `TestBackend` after scripted key presses and compares the screen
+//! with the text snapshots in `src/app/snapshots/`. Run with `UPDATE_SNAPSHOTS=1` to
+//! rewrite them after an intended UI change.
+
+use std::{fs, path::PathBuf};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use crossterm::event::KeyCode;
+use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+use crate::prelude::*;
+use arriva::utils::{Backend, Cassette, FixtureTransport, Replayer};
+use arriva::{ApiConfig, ArrivaClient};
+
+use super::screen::Screen;
+use super::App;
+
+const WIDTH: u16 = 100;
+const HEIGHT: u16 = 30;
+
+struct Harness {
+    app: App,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    /// An app over the fixture stops with the date and number format pinned
+    async fn new(backend: Backend) -> Self {
+        let client = ArrivaClient::with_transport(backend, ApiConfig::default());
+        let stops = ArrivaClient::with_transport(FixtureTransport, ApiConfig::default())
+            .stops()
+            .await
+            .unwrap();
+        let mut app = App::new(stops, client);
+        app.date = NaiveDate::from_ymd_opt(2024, 5, 20).unwrap();
+        app.decimal_separator = DecimalSeparator::Point;
+
+        Self {
+            app,
+            terminal: Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap(),
+        }
+    }
+
+    fn with_saved_routes(self, favourites: Favourites, history: History) -> Self {
+        Self {
+            app: self
+                .app
+                .with_favourites(favourites, None)
+                .with_history(history, None),
+            ..self
+        }
+    }
+
+    fn resize(&mut self, width: u16, height: u16) {
+        self.terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    }
+
+    fn press(&mut self, code: KeyCode) -> &mut Self {
+        self.app.handle_key_event(code.into());
+        self
+    }
+
+    fn type_text(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            self.press(KeyCode::Char(c));
+        }
+        self
+    }
+
+    /// Waits for the background fetch started by the last key press
+    async fn finish_fetch(&mut self) {
+        let receiver = self.app.receiver.as_mut().unwrap();
+        let message = receiver.recv().await.unwrap();
+        self.app.handle_message(message);
+    }
+
+    fn assert_snapshot(&mut self, name: &str) {
+        let app = &mut self.app;
+        self.terminal.draw(|frame| app.render_frame(frame)).unwrap();
+        let screen = buffer_to_text(self.terminal.backend().buffer());
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/app/snapshots")
+            .join(format!("{}.txt", name));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &screen).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "Missing snapshot {}, run the tests with UPDATE_SNAPSHOTS=1",
+                path.display()
+            )
+        });
+        assert!(
+            expected == screen,
+            "Snapshot {} changed, run the tests with UPDATE_SNAPSHOTS=1 if it is intended\n\
+             --- expected\n{}\n--- rendered\n{}",
+            name,
+            expected,
+            screen
+        );
+    }
+}
+
+/// The symbols of every row, without trailing spaces
+fn buffer_to_text(buffer: &Buffer) -> String {
+    let width = buffer.area.width as usize;
+    buffer
+        .content
+        .chunks(width)
+        .map(|row| {
+            let line: String = row.iter().map(|cell| cell.symbol()).collect();
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_origin_selection() {
+    let mut harness = Harness::new(Backend::Fixture(FixtureTransport)).await;
+    harness.type_text("coru");
+
+    harness.assert_snapshot("origin_selection");
+}
+
+#[tokio::test]
+async fn test_start_screen() {
+    let harness = Harness::new(Backend::Fixture(FixtureTransport)).await;
+    let stops = &harness.app.stops;
+    let favourites = Favourites {
+        routes: vec![
+            Route::new(&stops[0], &stops[1]),
+            Route::new(&stops[1], &stops[0]),
+        ],
+    };
+    let searched_on = |day| HistoryEntry {
+        route: Route::new(&stops[1], &stops[0]),
+        date: NaiveDate::from_ymd_opt(2024, 5, day).unwrap(),
+        searched_at: Local.with_ymd_and_hms(2024, 5, 19, 8, 0, 0).unwrap(),
+    };
+    let history = History {
+        entries: vec![searched_on(21), searched_on(20)],
+    };
+    let mut harness = harness.with_saved_routes(favourites, history);
+    harness.press(KeyCode::Down);
+
+    assert_eq!(harness.app.screen, Screen::Start);
+    harness.assert_snapshot("start");
+}
+
+#[tokio::test]
+async fn test_destination_selection() {
+    let mut harness = Harness::new(Backend::Fixture(FixtureTransport)).await;
+    harness
+        .type_text("coru")
+        .press(KeyCode::Enter)
+        .press(KeyCode::Down)
+        .press(KeyCode::Down);
+
+    assert_eq!(harness.app.screen, Screen::ChooseDestination);
+    harness.assert_snapshot("destination_selection");
+}
+
+#[tokio::test]
+async fn test_date_selection() {
+    let mut harness = Harness::new(Backend::Fixture(FixtureTransport)).await;
+    harness
+        .type_text("coru")
+        .press(KeyCode::Enter)
+        .type_text("laracha")
+        .press(KeyCode::Enter);
+
+    assert_eq!(harness.app.screen, Screen::ChooseDate);
+    harness.assert_snapshot("date_selection");
+}
+
+#[tokio::test]
+async fn test_results() {
+    let mut harness = Harness::new(Backend::Fixture(FixtureTransport)).await;
+    harness
+        .type_text("coru")
+        .press(KeyCode::Enter)
+        .type_text("laracha")
+        .press(KeyCode::Enter)
+        .press(KeyCode::Enter);
+    harness.finish_fetch().await;
+    harness.press(KeyCode::Tab).press(KeyCode::Down);
+
+    assert_eq!(harness.app.screen, Screen::Results);
+    harness.assert_snapshot("results");
+
+    harness.resize(140, 20);
+    harness.assert_snapshot("results_wide");
+}
+
+#[tokio::test]
+async fn test_error_popup() {
+    // an empty cassette fails every expedition request
+    let backend = Backend::Replay(Replayer::new(Cassette::default()));
+    let mut harness = Harness::new(backend).await;
+    harness
+        .type_text("coru")
+        .press(KeyCode::Enter)
+        .type_text("laracha")
+        .press(KeyCode::Enter)
+        .press(KeyCode::Enter);
+    harness.finish_fetch().await;
+
+    assert_eq!(harness.app.screen, Screen::Error);
+    harness.assert_snapshot("error_popup");
+}
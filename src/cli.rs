@@ -0,0 +1,326 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chrono::{DateTime, Days, FixedOffset, NaiveDate};
+use clap::{Args, Parser, Subcommand};
+
+use arriva::utils::{search_stops, Transport};
+use arriva::ArrivaClient;
+
+use crate::output::{write_records, Format};
+use crate::prelude::*;
+
+/// Exit code of invalid arguments, the same clap uses, and of an invalid config file
+pub const EXIT_USAGE: u8 = 2;
+/// Exit code when the command worked but found nothing: no stop matches the query,
+/// there are no expeditions on the date or none left today and tomorrow
+pub const EXIT_NOT_FOUND: u8 = 3;
+/// Exit code when `--from` or `--to` is not a known stop
+pub const EXIT_UNKNOWN_STOP: u8 = 4;
+
+/// Arriva Galicia bus times in the terminal. Without a command the interactive
+/// interface starts, the commands print what they find for scripts
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Cli {
+    /// Read the settings from this file instead of the config directory
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Serve the bundled fixtures instead of calling Arriva
+    #[arg(long, global = true, conflicts_with_all = ["record", "replay"])]
+    pub offline: bool,
+
+    /// Save every request and response into a cassette
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Answer every request from a recorded cassette
+    #[arg(long, global = true, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Download the stops again instead of using the cached ones
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    /// Append the latency of every request and every retry to this file,
+    /// `RUST_LOG` can change what is logged
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log: Option<PathBuf>,
+
+    /// How the commands print stops and expeditions
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub format: Format,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Print the id and name of the stops matching a search
+    Stops {
+        /// Words of the stop name, every stop when missing
+        #[arg(short, long, default_value = "")]
+        query: String,
+    },
+    /// Print the expeditions between two stops on a date
+    Search {
+        #[command(flatten)]
+        route: RouteArgs,
+
+        /// DD-MM-YYYY, today when missing
+        #[arg(short, long, value_parser = parse_date_arg)]
+        date: Option<NaiveDate>,
+    },
+    /// Print the next expedition between two stops, today or tomorrow
+    Next {
+        #[command(flatten)]
+        route: RouteArgs,
+    },
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct RouteArgs {
+    /// Stop id, or words of the name of the origin
+    #[arg(short, long)]
+    pub from: String,
+
+    /// Stop id, or words of the name of the destination
+    #[arg(short, long)]
+    pub to: String,
+}
+
+fn parse_date_arg(date: &str) -> std::result::Result<NaiveDate, String> {
+    parse_date(date).map_err(|err| err.to_string())
+}
+
+/// The exit code an error ends the program with
+pub fn exit_code(err: &Error) -> ExitCode {
+    match err {
+        Error::InvalidConfig { .. } => ExitCode::from(EXIT_USAGE),
+        Error::EmptyResults(_) => ExitCode::from(EXIT_NOT_FOUND),
+        Error::UnknownStop(_) => ExitCode::from(EXIT_UNKNOWN_STOP),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+/// Runs a command over the stop catalogue, printing what it found to `out` in `format`.
+/// `now` decides today and the next expedition
+pub async fn run<T: Transport>(
+    command: &Command,
+    client: &ArrivaClient<T>,
+    stops: &[Stop],
+    now: DateTime<FixedOffset>,
+    format: Format,
+    out: &mut impl Write,
+) -> Result<()> {
+    match command {
+        Command::Stops { query } => {
+            let found = search_stops(stops, query);
+            if found.is_empty() {
+                return Err(Error::EmptyResults("stops"));
+            }
+            write_records(out, format, &found)?;
+        }
+        Command::Search { route, date } => {
+            let route = find_route(stops, route)?;
+            let date = date.unwrap_or(now.date_naive());
+            let expeditions = outward_expeditions(client, &route, date).await?;
+            if expeditions.is_empty() {
+                return Err(Error::EmptyResults("expeditions"));
+            }
+            write_records(out, format, &expeditions)?;
+        }
+        Command::Next { route } => {
+            let route = find_route(stops, route)?;
+            let today = now.date_naive();
+            let mut next = None;
+            for date in [Some(today), today.checked_add_days(Days::new(1))]
+                .into_iter()
+                .flatten()
+            {
+                // a day without any service is not the end of the search
+                let expeditions = match outward_expeditions(client, &route, date).await {
+                    Err(Error::EmptyResults(_)) => Vec::new(),
+                    result => result?,
+                };
+                next = expeditions
+                    .into_iter()
+                    .find(|expedition| expedition.get_departure() >= now);
+                if next.is_some() {
+                    break;
+                }
+            }
+            let next = next.ok_or(Error::EmptyResults("expeditions"))?;
+            write_records(out, format, &[next])?;
+        }
+    }
+    Ok(())
+}
+
+async fn outward_expeditions<T: Transport>(
+    client: &ArrivaClient<T>,
+    (from, to): &(Stop, Stop),
+    date: NaiveDate,
+) -> Result<Vec<Expedition>> {
+    let (outward, _) = client.expeditions(from, to, date).await?;
+    Ok(outward)
+}
+
+fn find_route(stops: &[Stop], route: &RouteArgs) -> Result<(Stop, Stop)> {
+    Ok((find_stop(stops, &route.from)?, find_stop(stops, &route.to)?))
+}
+
+// a stop id, or the best match of a search
+fn find_stop(stops: &[Stop], stop: &str) -> Result<Stop> {
+    let found = match stop.trim().parse::<usize>() {
+        Ok(id) => stops.iter().find(|s| s.get_parada() == id).cloned(),
+        Err(_) => search_stops(stops, stop).into_iter().next(),
+    };
+    found.ok_or_else(|| Error::UnknownStop(stop.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arriva::utils::FixtureTransport;
+    use arriva::ApiConfig;
+
+    fn at(time: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(&format!("2024-05-20T{}:00+02:00", time)).unwrap()
+    }
+
+    async fn output(args: &[&str], now: DateTime<FixedOffset>) -> Result<String> {
+        output_with(FixtureTransport, args, now).await
+    }
+
+    async fn output_with<T: Transport>(
+        transport: T,
+        args: &[&str],
+        now: DateTime<FixedOffset>,
+    ) -> Result<String> {
+        let cli = Cli::try_parse_from([&["arriva-tui"], args].concat()).unwrap();
+        let mut out = Vec::new();
+        let command = cli.command.unwrap();
+        let client = ArrivaClient::with_transport(transport, ApiConfig::default());
+        let stops = client.stops().await?;
+        run(&command, &client, &stops, now, cli.format, &mut out).await?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    /// No service at all on 20-05-2024, the fixture expeditions moved to the next day
+    struct NoServiceToday;
+
+    impl Transport for NoServiceToday {
+        async fn post(&self, endpoint: &str, content_type: &str, body: &str) -> Result<String> {
+            if body.contains("20-05-2024") {
+                return Ok(r#"{"expediciones":{"ida":[],"vuelta":[]}}"#.to_string());
+            }
+            let response = FixtureTransport.post(endpoint, content_type, body).await?;
+            Ok(response.replace("2024-05-20", "2024-05-21"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stops() {
+        let out = output(&["stops", "--query", "laracha"], at("10:00"))
+            .await
+            .unwrap();
+        assert_eq!(out.lines().next(), Some("5714\tLaracha (LARACHA)"));
+
+        let err = output(&["stops", "-q", "nowhere at all"], at("10:00")).await;
+        assert!(matches!(err, Err(Error::EmptyResults("stops"))));
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let out = output(
+            &[
+                "search",
+                "--from",
+                "5274",
+                "--to",
+                "laracha",
+                "-d",
+                "20-05-2024",
+            ],
+            at("10:00"),
+        )
+        .await
+        .unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "07:30\t08:05\t35m\t2.35\tA Coruña - Laracha - Carballo"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_next() {
+        let out = output(&["next", "-f", "coruña", "-t", "5714"], at("10:00"))
+            .await
+            .unwrap();
+        assert!(out.starts_with("13:15\t13:50"), "{}", out);
+
+        // the fixtures have nothing after the last bus, neither today nor tomorrow
+        let err = output(&["next", "-f", "5274", "-t", "5714"], at("21:00")).await;
+        assert!(matches!(err, Err(Error::EmptyResults("expeditions"))));
+    }
+
+    #[tokio::test]
+    async fn test_next_without_service_today() {
+        let args = ["next", "-f", "5274", "-t", "5714", "--format", "csv"];
+        let out = output_with(NoServiceToday, &args, at("10:00"))
+            .await
+            .unwrap();
+        assert_eq!(
+            out.lines().nth(1).map(|line| line.split(',').nth(1)),
+            Some(Some("2024-05-21T07:30:00+02:00"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_formats() {
+        let args = ["next", "-f", "5274", "-t", "5714", "--format"];
+        let csv = output(&[&args[..], &["csv"]].concat(), at("10:00"))
+            .await
+            .unwrap();
+        assert_eq!(
+            csv,
+            "name,departure,arrival,duration_minutes,arrives_next_day,fare_cents,currency\n\
+             A Coruña - Laracha - Carballo,2024-05-20T13:15:00+02:00,2024-05-20T13:50:00+02:00,35,false,235,EUR\n"
+        );
+
+        let json = output(&[&args[..], &["json"]].concat(), at("10:00"))
+            .await
+            .unwrap();
+        let json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json[0]["departure"], "2024-05-20T13:15:00+02:00");
+        assert_eq!(json[0]["fare"]["basic"]["cents"], 235);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_stop() {
+        let err = output(&["next", "-f", "99999", "-t", "5714"], at("10:00")).await;
+        assert!(matches!(err, Err(Error::UnknownStop(ref stop)) if stop == "99999"));
+    }
+
+    #[test]
+    fn test_invalid_date() {
+        let result = Cli::try_parse_from([
+            "arriva-tui",
+            "search",
+            "-f",
+            "1",
+            "-t",
+            "2",
+            "-d",
+            "31-02-2024",
+        ]);
+        assert!(result.is_err());
+    }
+}
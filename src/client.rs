@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::prelude::*;
+use crate::utils::{Conditional, ReqwestTransport, Transport};
+
+pub const STOPS_URL: &str = "https://arriva.gal/plataforma/api/superparadas/index/buscador.json";
+pub const EXPEDITIONS_URL: &str = "https://arriva.es/es/galicia/para-viajar/arriva";
+
+const STOPS_CONTENT_TYPE: &str = "application/json; charset=UTF-8";
+const STOPS_PAYLOAD: &str = r#"{"key":"value"}"#;
+
+/// Sent with every request, so Arriva can tell who is calling
+pub const USER_AGENT: &str = concat!("arriva-tui/", env!("CARGO_PKG_VERSION"));
+
+/// Where and how the Arriva APIs are called, the `[api]` section of the config file
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub stops_url: String,
+    pub expeditions_url: String,
+    /// Longest wait for the connection to open
+    pub connect_timeout_secs: u64,
+    /// Longest wait for the next bytes of the response, `timeout_secs` in older files
+    #[serde(alias = "timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// Times a request is sent again after a server error, a refused connection or a timeout
+    pub retries: u32,
+    /// Wait before the first retry, doubled on every other one
+    pub retry_delay_ms: u64,
+    pub user_agent: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            stops_url: STOPS_URL.to_string(),
+            expeditions_url: EXPEDITIONS_URL.to_string(),
+            connect_timeout_secs: 5,
+            read_timeout_secs: 10,
+            retries: 2,
+            retry_delay_ms: 500,
+            user_agent: USER_AGENT.to_string(),
+        }
+    }
+}
+
+impl ApiConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs)
+    }
+}
+
+/// Queries the Arriva Galicia stops and expeditions over a `Transport`, HTTP by default
+#[derive(Debug, Clone)]
+pub struct ArrivaClient<T = ReqwestTransport> {
+    transport: T,
+    api: ApiConfig,
+}
+
+impl ArrivaClient {
+    /// A client calling the real APIs over HTTP, keeping its connections open between
+    /// requests. Fails when the user agent is not a valid header or TLS is unavailable
+    pub fn new(api: ApiConfig) -> Result<Self> {
+        Ok(Self::with_transport(ReqwestTransport::new(&api)?, api))
+    }
+}
+
+impl<T: Transport> ArrivaClient<T> {
+    /// A client sending its requests through `transport`, such as the fixtures or a cassette
+    pub fn with_transport(transport: T, api: ApiConfig) -> Self {
+        Self { transport, api }
+    }
+
+    pub fn api(&self) -> &ApiConfig {
+        &self.api
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Every stop Arriva serves, failing when there are none
+    pub async fn stops(&self) -> Result<Vec<Stop>> {
+        let response = self
+            .transport
+            .post(&self.api.stops_url, STOPS_CONTENT_TYPE, STOPS_PAYLOAD)
+            .await?;
+        deserialize_stops(response)
+    }
+
+    /// The catalogue again, only downloading it when Arriva says it changed since `cached`
+    pub async fn refresh_stops(&self, cached: Option<&StopCache>) -> Result<StopCache> {
+        let validators = cached
+            .map(|cache| cache.validators.clone())
+            .unwrap_or_default();
+        let response = self
+            .transport
+            .post_if_modified(
+                &self.api.stops_url,
+                STOPS_CONTENT_TYPE,
+                STOPS_PAYLOAD,
+                &validators,
+            )
+            .await?;
+
+        match (response, cached) {
+            (Conditional::Modified(body, validators), _) => {
+                Ok(StopCache::new(deserialize_stops(body)?, validators))
+            }
+            (Conditional::NotModified, Some(cached)) => Ok(cached.clone().touched()),
+            (Conditional::NotModified, None) => self
+                .stops()
+                .await
+                .map(|stops| StopCache::new(stops, Default::default())),
+        }
+    }
+
+    /// The outward and return expeditions between two stops on a date, failing when
+    /// there are none in either direction
+    pub async fn expeditions(
+        &self,
+        from: &Stop,
+        to: &Stop,
+        date: NaiveDate,
+    ) -> Result<(Vec<Expedition>, Vec<Expedition>)> {
+        let request =
+            ExpeditionRequest::from_stops((from, to), date.format(DATE_FORMAT).to_string());
+        let response = self
+            .transport
+            .post(
+                &self.api.expeditions_url,
+                "application/x-www-form-urlencoded; charset=UTF-8",
+                &request.get_payload(),
+            )
+            .await?;
+        let value: Value = serde_json::from_str(&response)?;
+        deserialize_expeditions(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{FixtureTransport, Validators};
+
+    fn client() -> ArrivaClient<FixtureTransport> {
+        ArrivaClient::with_transport(FixtureTransport, ApiConfig::default())
+    }
+
+    fn coruna() -> Stop {
+        Stop::new(
+            5274,
+            "Estación de Coruña (A CORUÑA)".to_string(),
+            "Estación de Coruña".to_string(),
+            516,
+            Some(43.3531),
+            Some(-8.4053),
+            Some(43.3531),
+            Some(-8.4053),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_stops() {
+        let stops = client().stops().await.unwrap();
+        assert_eq!(stops[0], coruna());
+    }
+
+    #[tokio::test]
+    async fn test_expeditions() {
+        let client = client();
+        let stops = client.stops().await.unwrap();
+        let laracha = stops.iter().find(|stop| stop.get_parada() == 5714).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 5, 20).unwrap();
+
+        let (outward, ret) = client.expeditions(&coruna(), laracha, date).await.unwrap();
+        assert_eq!(outward.len(), 3);
+        assert_eq!(outward[0].get_name(), "A Coruña - Laracha - Carballo");
+        assert!(!ret.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_stops() {
+        let cache = client().refresh_stops(None).await.unwrap();
+        assert_eq!(cache.stops[0], coruna());
+        assert_eq!(cache.validators, Validators::default());
+    }
+}
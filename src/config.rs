@@ -0,0 +1,376 @@
+use std::{fmt, fs, io::ErrorKind, path::Path, str::FromStr};
+
+use chrono::TimeDelta;
+use crossterm::event::KeyCode;
+use ratatui::style::Color;
+use serde::{de::IgnoredAny, Deserialize};
+
+use arriva::ApiConfig;
+
+use crate::prelude::*;
+use crate::theme::{Theme, ThemeName};
+
+/// Furthest a default date can be from today
+const MAX_DATE_OFFSET: u32 = 365;
+/// More retries would keep the app waiting for minutes on a dead server
+const MAX_RETRIES: u32 = 10;
+
+/// Everything read from `config.toml`, every section and key is optional
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub defaults: Defaults,
+    pub theme: ThemeConfig,
+    pub keys: KeyMap,
+    pub cache: CacheConfig,
+}
+
+/// What the app starts with
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// Stop id highlighted in the From list
+    pub origin: Option<usize>,
+    /// Days after today of the initial date
+    pub date_offset: u32,
+}
+
+/// How long the cached stop catalogue is used before asking Arriva again
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// `0` refreshes the stops on every start, still showing the cached ones meanwhile
+    pub stops_ttl_hours: u32,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            stops_ttl_hours: 24,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn stops_ttl(&self) -> TimeDelta {
+        TimeDelta::hours(self.stops_ttl_hours.into())
+    }
+}
+
+/// A built-in theme and the colours to change in it, as names (`cyan`, `lightred`...),
+/// `#rrggbb` or 256 colour indexes
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: ThemeName,
+    pub primary: Option<String>,
+    pub secondary: Option<String>,
+    pub error: Option<String>,
+    /// No longer used, the terminal keeps its own background. Still accepted so
+    /// config files written for older versions load
+    #[serde(rename = "background")]
+    pub deprecated_background: Option<IgnoredAny>,
+}
+
+/// The keys of the app actions, the arrows, pages, `Enter` and `Ctrl+C` are fixed
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyMap {
+    pub quit: Key,
+    pub back: Key,
+    pub change_date: Key,
+    pub swap: Key,
+    pub star: Key,
+    pub switch_focus: Key,
+    pub retry: Key,
+    pub remove: Key,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            quit: Key(KeyCode::Char('q')),
+            back: Key(KeyCode::Esc),
+            change_date: Key(KeyCode::Char('d')),
+            swap: Key(KeyCode::Char('s')),
+            star: Key(KeyCode::Char('f')),
+            switch_focus: Key(KeyCode::Tab),
+            retry: Key(KeyCode::Char('r')),
+            remove: Key(KeyCode::Delete),
+        }
+    }
+}
+
+impl KeyMap {
+    fn bindings(&self) -> [(&'static str, Key); 8] {
+        [
+            ("quit", self.quit),
+            ("back", self.back),
+            ("change_date", self.change_date),
+            ("swap", self.swap),
+            ("star", self.star),
+            ("switch_focus", self.switch_focus),
+            ("retry", self.retry),
+            ("remove", self.remove),
+        ]
+    }
+}
+
+/// A single key, written as a character or as one of `esc`, `tab`, `delete`, `backspace`,
+/// `insert`, `home`, `end` and `f1`..`f12`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Key(pub KeyCode);
+
+impl PartialEq<KeyCode> for Key {
+    fn eq(&self, code: &KeyCode) -> bool {
+        match (self.0, code) {
+            // `Q` is typed with shift but means the same action
+            (KeyCode::Char(a), KeyCode::Char(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == *b,
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        let mut chars = text.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key(KeyCode::Char(c.to_ascii_lowercase())));
+        }
+
+        let code = match text.to_ascii_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "delete" | "del" => KeyCode::Delete,
+            "backspace" => KeyCode::Backspace,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "space" => KeyCode::Char(' '),
+            name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key `{}`", text)),
+            },
+        };
+        Ok(Key(code))
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(text: String) -> std::result::Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl Config {
+    /// Reads and validates the config file, a missing file at the default location just
+    /// means the defaults are used
+    pub fn load(path: &Path, required: bool) -> Result<Self> {
+        let invalid = |message: String| Error::InvalidConfig {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(err) => return Err(invalid(err.to_string())),
+        };
+        let config: Config = toml::from_str(&content).map_err(|err| invalid(err.to_string()))?;
+        config.validate().map_err(invalid)?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        for (key, url) in [
+            ("api.stops_url", &self.api.stops_url),
+            ("api.expeditions_url", &self.api.expeditions_url),
+        ] {
+            let parsed = reqwest::Url::parse(url).map_err(|err| format!("{}: {}", key, err))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(format!("{}: expected an http or https URL", key));
+            }
+        }
+        for (key, secs) in [
+            ("api.connect_timeout_secs", self.api.connect_timeout_secs),
+            ("api.read_timeout_secs", self.api.read_timeout_secs),
+        ] {
+            if secs == 0 {
+                return Err(format!("{}: must be at least 1", key));
+            }
+        }
+        if self.api.retries > MAX_RETRIES {
+            return Err(format!("api.retries: must be at most {}", MAX_RETRIES));
+        }
+        let user_agent = self.api.user_agent.trim();
+        if user_agent.is_empty() {
+            return Err("api.user_agent: must not be empty".to_string());
+        }
+        if reqwest::header::HeaderValue::from_str(user_agent).is_err() {
+            return Err("api.user_agent: must not contain control characters".to_string());
+        }
+        if self.defaults.date_offset > MAX_DATE_OFFSET {
+            return Err(format!(
+                "defaults.date_offset: must be at most {}",
+                MAX_DATE_OFFSET
+            ));
+        }
+
+        self.theme()?;
+
+        let bindings = self.keys.bindings();
+        for (i, (action, key)) in bindings.iter().enumerate() {
+            if let Some((other, _)) = bindings[..i].iter().find(|(_, other)| other == key) {
+                return Err(format!(
+                    "keys.{}: <{}> is already bound to keys.{}",
+                    action, key, other
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The chosen built-in theme with the configured colours on top, in full colour
+    pub fn theme(&self) -> std::result::Result<Theme, String> {
+        let name = self.theme.name;
+        let mut colors = name.colors();
+        for (key, value, color) in [
+            ("primary", &self.theme.primary, &mut colors.primary),
+            ("secondary", &self.theme.secondary, &mut colors.secondary),
+            ("error", &self.theme.error, &mut colors.error),
+        ] {
+            if let Some(value) = value {
+                *color = Color::from_str(value)
+                    .map_err(|_| format!("theme.{}: unknown colour `{}`", key, value))?;
+            }
+        }
+        Ok(Theme::new(name, colors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use arriva::STOPS_URL;
+
+    fn parse(content: &str) -> std::result::Result<Config, String> {
+        let config: Config = toml::from_str(content).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_empty_config_is_default() {
+        assert_eq!(parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_full_config() {
+        let config = parse(
+            r##"
+            [api]
+            timeout_secs = 3
+            retries = 0
+            user_agent = "my-script/1.0"
+
+            [defaults]
+            origin = 5274
+            date_offset = 1
+
+            [theme]
+            name = "high-contrast"
+            primary = "#ff8800"
+            background = "#000000"
+
+            [cache]
+            stops_ttl_hours = 0
+
+            [keys]
+            quit = "x"
+            remove = "d"
+            change_date = "F2"
+            "##,
+        )
+        .unwrap();
+
+        // `timeout_secs` is still read, as the read timeout
+        assert_eq!(config.api.read_timeout(), Duration::from_secs(3));
+        assert_eq!(config.api.connect_timeout(), Duration::from_secs(5));
+        assert_eq!(config.api.retries, 0);
+        assert_eq!(config.api.stops_url, STOPS_URL);
+        assert_eq!(config.defaults.origin, Some(5274));
+        assert_eq!(config.cache.stops_ttl(), TimeDelta::zero());
+        assert_eq!(config.keys.quit, KeyCode::Char('X'));
+        assert_eq!(config.keys.change_date.to_string(), "F2");
+        let theme = config.theme().unwrap();
+        assert_eq!(theme.title.fg, Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(theme.muted.fg, Some(Color::LightYellow));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        for (content, expected) in [
+            ("[api]\nstops_url = \"arriva\"", "api.stops_url"),
+            ("[api]\ntimeout_secs = 0", "api.read_timeout_secs"),
+            (
+                "[api]\nconnect_timeout_secs = 0",
+                "api.connect_timeout_secs",
+            ),
+            ("[api]\nretries = 50", "api.retries"),
+            ("[api]\nuser_agent = \"a\\nb\"", "api.user_agent"),
+            ("[defaults]\ndate_offset = 400", "defaults.date_offset"),
+            ("[theme]\nprimary = \"blurple\"", "theme.primary"),
+            (
+                "[theme]\nname = \"solarized\"",
+                "unknown variant `solarized`",
+            ),
+            (
+                "[keys]\nswap = \"d\"",
+                "keys.swap: <D> is already bound to keys.change_date",
+            ),
+            ("[keys]\nquit = \"ctrl\"", "unknown key `ctrl`"),
+            ("[colours]\nprimary = \"red\"", "unknown field `colours`"),
+        ] {
+            let err = parse(content).unwrap_err();
+            assert!(err.contains(expected), "{:?} gave {:?}", content, err);
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let path = Path::new("/nonexistent/arriva-tui/config.toml");
+        assert_eq!(Config::load(path, false).unwrap(), Config::default());
+        assert!(matches!(
+            Config::load(path, true),
+            Err(Error::InvalidConfig { .. })
+        ));
+    }
+}
@@ -4,6 +4,37 @@ pub enum Error {
     #[error("Generic: {0}")]
     Generic(String),
 
+    #[error("{endpoint} answered with HTTP status {status}")]
+    Status { status: u16, endpoint: String },
+
+    #[error("Malformed stop payload: {0}")]
+    MalformedStops(#[source] serde_json::Error),
+
+    #[error("Malformed expedition payload: no {0} expeditions array")]
+    MalformedExpeditions(&'static str),
+
+    #[error("Missing or invalid `{field}` in {direction} expedition {index}")]
+    MissingExpeditionField {
+        direction: &'static str,
+        index: usize,
+        field: &'static str,
+    },
+
+    #[error("Invalid date `{0}`, expected DD-MM-YYYY")]
+    InvalidDate(String),
+
+    #[error("Invalid config {path}: {message}")]
+    InvalidConfig { path: String, message: String },
+
+    #[error("Unknown stop {0}")]
+    UnknownStop(String),
+
+    #[error("No {0} found")]
+    EmptyResults(&'static str),
+
+    #[error("No recorded interaction for {endpoint} with payload {payload}")]
+    UnmatchedRequest { endpoint: String, payload: String },
+
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
@@ -0,0 +1,26 @@
+//! Arriva Galicia bus stops, expeditions and fares.
+//!
+//! ```no_run
+//! use arriva::{ApiConfig, ArrivaClient};
+//!
+//! # async fn run() -> arriva::Result<()> {
+//! let client = ArrivaClient::new(ApiConfig::default())?;
+//! let stops = client.stops().await?;
+//! let date = chrono::Local::now().date_naive();
+//! let (outward, _) = client.expeditions(&stops[0], &stops[1], date).await?;
+//! for expedition in outward {
+//!     println!("{} {}", expedition.get_departure(), expedition.get_name());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod client;
+pub mod error;
+pub mod prelude;
+pub mod structures;
+pub mod utils;
+
+pub use client::{ApiConfig, ArrivaClient, EXPEDITIONS_URL, STOPS_URL, USER_AGENT};
+pub use error::Error;
+pub use prelude::Result;
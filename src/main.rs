@@ -1,171 +1,233 @@
-#![allow(unused)]
 use crate::prelude::*;
-use async_std::task::sleep;
 use chrono::prelude::Local;
-use crossterm::terminal;
-use ratatui::{backend::CrosstermBackend, prelude::buffer::Buffer, Terminal};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::fs::OpenOptions;
 use std::io::Write;
-use std::{io::Stdout, thread, time::Duration};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::{io::Stdout, time::Duration};
 use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 
 mod app;
-mod error;
-mod prelude;
-mod structures;
-mod utils;
+mod cli;
+mod config;
+mod output;
+mod paths;
+mod theme;
+
+mod prelude {
+    pub use crate::theme::theme;
+    pub use arriva::prelude::*;
+}
 
 use app::App;
-use structures::*;
-use utils::fetch_data;
+use arriva::utils::{Backend, FixtureTransport, Recorder, Replayer, ReqwestTransport, Transport};
+use arriva::{ApiConfig, ArrivaClient};
+use clap::Parser;
+use cli::Cli;
+use config::Config;
+use theme::{set_theme, ColorDepth};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("arriva-tui: {}", err);
+            cli::exit_code(&err)
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    if let Some(path) = &cli.log {
+        init_log(path)?;
+    }
+    let config = load_config(&cli)?;
+    let backend = select_backend(&cli, &config.api)?;
+    // only the real catalogue is cached, not the fixtures or a cassette
+    let cache_path = match backend {
+        Backend::Reqwest(_) => paths::stops_cache_path(),
+        _ => None,
+    };
+    let client = ArrivaClient::with_transport(backend, config.api.clone());
+    let cached = match &cache_path {
+        Some(path) if !cli.refresh => StopCache::load(path),
+        _ => None,
+    };
+    let stale = match &cached {
+        Some(cache) => cache.is_stale(config.cache.stops_ttl(), Local::now()),
+        None => true,
+    };
+
+    if let Some(command) = &cli.command {
+        // a single command waits for stale stops to be refreshed, and keeps using
+        // them when the refresh fails
+        let cache = match cached {
+            Some(cache) if !stale => cache,
+            Some(cache) => {
+                match refresh_stops(&client, Some(&cache), cache_path.as_deref()).await {
+                    Ok(refreshed) => refreshed,
+                    Err(err) => {
+                        tracing::warn!(error = %err, "could not refresh the stops");
+                        cache
+                    }
+                }
+            }
+            None => refresh_stops(&client, None, cache_path.as_deref()).await?,
+        };
+        let now = Local::now().fixed_offset();
+        let mut stdout = std::io::stdout().lock();
+        return cli::run(command, &client, &cache.stops, now, cli.format, &mut stdout).await;
+    }
+
+    set_theme(
+        config
+            .theme()
+            .unwrap_or_default()
+            .with_depth(ColorDepth::from_env()),
+    );
+    let favourites_path = paths::favourites_path();
+    let favourites = match &favourites_path {
+        Some(path) => Favourites::load(path),
+        None => Favourites::default(),
+    };
+    let history_path = paths::history_path();
+    let history = match &history_path {
+        Some(path) => History::load(path),
+        None => History::default(),
+    };
+
+    app::install_panic_hook();
     let mut terminal = app::init()?;
 
-    let stops = fetch_initial_data(&mut terminal).await?;
-    terminal.set_cursor(0, 0);
-    thread::sleep(Duration::from_secs(1));
+    // cached stops are shown at once, and refreshed in the background when stale
+    let catalogue = match cached {
+        Some(cache) => Ok((cache, stale)),
+        None => fetch_initial_data(&mut terminal, &client, cache_path.as_deref())
+            .await
+            .map(|cache| (cache, false)),
+    };
+    let tui_app = catalogue.and_then(|(cache, stale)| {
+        App::new(cache.stops.clone(), client)
+            .with_config(&config)
+            .map(|app| app.with_stop_cache(cache, cache_path, stale))
+    });
+    let tui_app = match tui_app {
+        Ok(tui_app) => tui_app,
+        Err(err) => {
+            app::restore()?;
+            return Err(err);
+        }
+    };
+    terminal.set_cursor(0, 0)?;
     terminal.clear()?;
 
-    let app_result = App::new(stops).run(&mut terminal).await;
+    let app_result = tui_app
+        .with_favourites(favourites, favourites_path)
+        .with_history(history, history_path)
+        .run(&mut terminal)
+        .await;
     app::restore()?;
     app_result
 }
 
-async fn fetch_initial_data(
+// `--config <file>` replaces `config.toml` in the config directory, which may not exist
+fn load_config(cli: &Cli) -> Result<Config> {
+    match &cli.config {
+        Some(path) => Config::load(path, true),
+        None => match paths::config_path() {
+            Some(path) => Config::load(&path, false),
+            None => Ok(Config::default()),
+        },
+    }
+}
+
+// the requests are traced at debug level, shown unless `RUST_LOG` says otherwise
+fn init_log(path: &Path) -> Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("arriva=debug"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .init();
+    Ok(())
+}
+
+// `--offline` serves the bundled fixtures, `--record <file>` captures the real traffic
+// into a cassette and `--replay <file>` answers every request from one
+fn select_backend(cli: &Cli, api: &ApiConfig) -> Result<Backend> {
+    if let Some(path) = &cli.replay {
+        Ok(Backend::Replay(Replayer::from_file(path)?))
+    } else if let Some(path) = cli.record.clone() {
+        Ok(Backend::Record(Recorder::new(
+            ReqwestTransport::new(api)?,
+            path,
+        )))
+    } else if cli.offline {
+        Ok(Backend::Fixture(FixtureTransport))
+    } else {
+        Ok(Backend::Reqwest(ReqwestTransport::new(api)?))
+    }
+}
+
+async fn fetch_initial_data<T: Transport>(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-) -> Result<Vec<Stop>> {
-    let mut bufwtr = BufferWriter::stderr(ColorChoice::Always);
+    client: &ArrivaClient<T>,
+    cache_path: Option<&Path>,
+) -> Result<StopCache> {
+    let bufwtr = BufferWriter::stderr(ColorChoice::Always);
     let mut buffer = bufwtr.buffer();
-    buffer.set_color(ColorSpec::new().set_fg(Some(PRIMARY_COLOR_TC)))?;
+    let color = theme().title.fg.and_then(terminal_color);
+    buffer.set_color(ColorSpec::new().set_fg(color))?;
     writeln!(&mut buffer, "Fetching data...")?;
     bufwtr.print(&buffer)?;
-    terminal.set_cursor(0, 0);
+    terminal.set_cursor(0, 0)?;
     buffer.clear();
 
-    let stops = get_stops().await?;
+    let cache = refresh_stops(client, None, cache_path).await?;
 
     writeln!(&mut buffer, "Data fetched successfully!")?;
     bufwtr.print(&buffer)?;
-    terminal.set_cursor(0, 0);
-
-    Ok(stops)
-}
+    terminal.set_cursor(0, 0)?;
+    // long enough to read it, cached stops skip this screen
+    tokio::time::sleep(Duration::from_secs(1)).await;
 
-async fn get_stops() -> Result<Vec<Stop>> {
-    match fetch_data(
-        "https://arriva.gal/plataforma/api/superparadas/index/buscador.json",
-        "application/json; charset=UTF-8",
-        r#"{"key":"value"}"#,
-    )
-    .await
-    {
-        Ok(response) => match deserialize_stops(response) {
-            Ok(stops) => Ok(stops),
-            Err(error) => return Err(error.into()),
-        },
-        Err(e) => return Err(e.into()),
-    }
+    Ok(cache)
 }
 
-async fn get_expeditions(stops: (&Stop, &Stop), date: Option<String>) -> Result<Value> {
-    let date = match date {
-        Some(date) => date,
-        None => Local::now().format("%d-%m-%Y").to_string(),
-    };
-
-    let expedition_req = ExpeditionRequest::from_stops(stops, date);
-    match fetch_data(
-        "https://arriva.es/es/galicia/para-viajar/arriva",
-        "application/x-www-form-urlencoded; charset=UTF-8",
-        &expedition_req.get_payload(),
-    )
-    .await
-    {
-        Ok(response) => {
-            let parsed: Value = match serde_json::from_str(&response) {
-                Ok(parsed) => parsed,
-                Err(error) => return Err(error.into()),
-            };
-            Ok(parsed)
-        }
-        Err(e) => return Err(e.into()),
+// asks Arriva for the stops unless they did not change since `cached`, and caches them
+async fn refresh_stops<T: Transport>(
+    client: &ArrivaClient<T>,
+    cached: Option<&StopCache>,
+    cache_path: Option<&Path>,
+) -> Result<StopCache> {
+    let cache = client.refresh_stops(cached).await?;
+    if let Some(path) = cache_path {
+        cache.save_or_warn(path);
     }
+    Ok(cache)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    async fn test_get_stops() {
-        let stop_1 = Stop::new(
-            5274,
-            "Estación de Coruña (A CORUÑA)".to_string(),
-            "Estación de Coruña".to_string(),
-            516,
-            Some(43.3531),
-            Some(-8.4053),
-            Some(43.3531),
-            Some(-8.4053),
-        );
-
-        let stops = match get_stops().await {
-            Ok(stops) => {
-                // println!("Stops: {:?}", stops);
-                stops
-            }
-            Err(err) => panic!("Error fetching stops: \n {:?}", err),
-        };
-
-        assert_eq!(stops[0], stop_1);
-    }
-
-    #[tokio::test]
-    async fn test_get_expeditions() {
-        let stop_1 = Stop::new(
-            5274,
-            "Estación de Coruña (A CORUÑA)".to_string(),
-            "Estación de Coruña".to_string(),
-            516,
-            Some(43.3531),
-            Some(-8.4053),
-            Some(43.3531),
-            Some(-8.4053),
-        );
-
-        let stop_2 = Stop::new(
-            5714,
-            "Laracha (LARACHA)".to_string(),
-            "Laracha".to_string(),
-            121,
-            Some(43.2492),
-            Some(-8.5872),
-            Some(43.2492),
-            Some(-8.5872),
-        );
-
-        let date = Local::now().format("%d-%m-%Y").to_string();
-
-        let expeditions_value: Value = match get_expeditions((&stop_1, &stop_2), Some(date)).await {
-            Ok(expeditions) => expeditions,
-            Err(err) => panic!("Error fetching expeditions: \n {:?}", err),
-        };
-
-        let expeditions: (Vec<Expedition>, Vec<Expedition>) =
-            match deserialize_expeditions(expeditions_value) {
-                Ok(expeditions) => expeditions,
-                Err(err) => panic!("Error deserializing expeditions: \n {:?}", err),
-            };
-
-        println!("Outward expeditions:");
-        for expedition in expeditions.0 {
-            println!("{}", expedition);
-        }
-
-        println!("\nReturn expeditions:");
-        for expedition in expeditions.1 {
-            println!("{}", expedition);
-        }
+// the same colour for the plain stderr output, `None` leaves the default one
+fn terminal_color(color: ratatui::style::Color) -> Option<Color> {
+    use ratatui::style::Color as Tui;
+    match color {
+        Tui::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+        Tui::Indexed(i) => Some(Color::Ansi256(i)),
+        Tui::Black | Tui::DarkGray => Some(Color::Black),
+        Tui::Red | Tui::LightRed => Some(Color::Red),
+        Tui::Green | Tui::LightGreen => Some(Color::Green),
+        Tui::Yellow | Tui::LightYellow => Some(Color::Yellow),
+        Tui::Blue | Tui::LightBlue => Some(Color::Blue),
+        Tui::Magenta | Tui::LightMagenta => Some(Color::Magenta),
+        Tui::Cyan | Tui::LightCyan => Some(Color::Cyan),
+        Tui::Gray | Tui::White => Some(Color::White),
+        Tui::Reset => None,
     }
 }
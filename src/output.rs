@@ -0,0 +1,263 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// How the commands print what they found
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Readable values separated by tabs, without a header
+    #[default]
+    Text,
+    /// Readable values in aligned columns under a header
+    Table,
+    /// The schema columns separated by tabs, under a header
+    Tsv,
+    /// The schema columns separated by commas, under a header
+    Csv,
+    /// A JSON array of records
+    Json,
+    /// One JSON record per line
+    Ndjson,
+}
+
+/// Something the commands print, with a readable view for `text` and `table` and a
+/// flat view of its schema for `tsv` and `csv`
+pub trait Record: Serialize {
+    /// Headers of the readable view
+    const HEADERS: &'static [&'static str];
+    /// Names of the flat schema columns
+    const COLUMNS: &'static [&'static str];
+
+    fn cells(&self) -> Vec<String>;
+    fn fields(&self) -> Vec<String>;
+}
+
+impl Record for Stop {
+    const HEADERS: &'static [&'static str] = &["Id", "Name"];
+    const COLUMNS: &'static [&'static str] =
+        &["id", "name", "web_name", "weight", "latitude", "longitude"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.get_parada().to_string(), self.get_nombre()]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let coordinate = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        vec![
+            self.get_parada().to_string(),
+            self.get_nombre(),
+            self.get_nom_web(),
+            self.get_peso().to_string(),
+            coordinate(self.get_latitude()),
+            coordinate(self.get_longitude()),
+        ]
+    }
+}
+
+impl Record for Expedition {
+    const HEADERS: &'static [&'static str] = &["Departure", "Arrival", "Duration", "Fare", "Line"];
+    const COLUMNS: &'static [&'static str] = &[
+        "name",
+        "departure",
+        "arrival",
+        "duration_minutes",
+        "arrives_next_day",
+        "fare_cents",
+        "currency",
+    ];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.get_departure().format("%H:%M").to_string(),
+            format_arrival(self),
+            format_duration(self.get_duration()),
+            self.get_fare().basic.format_amount(DecimalSeparator::Point),
+            self.get_name(),
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let fare = self.get_fare().basic;
+        vec![
+            self.get_name(),
+            self.get_departure().to_rfc3339(),
+            self.get_arrival().to_rfc3339(),
+            self.get_duration().num_minutes().to_string(),
+            self.arrives_next_day().to_string(),
+            fare.get_cents().to_string(),
+            fare.get_currency().code().to_string(),
+        ]
+    }
+}
+
+/// Prints the records in the format
+pub fn write_records<R: Record>(out: &mut impl Write, format: Format, records: &[R]) -> Result<()> {
+    match format {
+        Format::Text => {
+            for record in records {
+                writeln!(out, "{}", record.cells().join("\t"))?;
+            }
+        }
+        Format::Table => write_table(out, R::HEADERS, records.iter().map(R::cells))?,
+        Format::Tsv => {
+            writeln!(out, "{}", R::COLUMNS.join("\t"))?;
+            for record in records {
+                let fields: Vec<String> = record.fields().iter().map(|f| tsv_field(f)).collect();
+                writeln!(out, "{}", fields.join("\t"))?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "{}", R::COLUMNS.join(","))?;
+            for record in records {
+                let fields: Vec<String> = record.fields().iter().map(|f| csv_field(f)).collect();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)?;
+        }
+        Format::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// every column as wide as its longest cell, the last one is not padded
+fn write_table(
+    out: &mut impl Write,
+    headers: &[&str],
+    rows: impl Iterator<Item = Vec<String>>,
+) -> io::Result<()> {
+    let header: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let rows: Vec<Vec<String>> = std::iter::once(header).chain(rows).collect();
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i + 1 == row.len() {
+                line.push_str(cell);
+            } else {
+                let padding = widths[i] - cell.chars().count();
+                line.push_str(cell);
+                line.push_str(&" ".repeat(padding + 2));
+            }
+        }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+// quoted only when needed, with inner quotes doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// TSV has no quoting, tabs and line breaks in a value become spaces
+fn tsv_field(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops() -> Vec<Stop> {
+        vec![
+            Stop::new(
+                5274,
+                "Estación de Coruña (A CORUÑA)".to_string(),
+                "Estación de Coruña".to_string(),
+                516,
+                None,
+                None,
+                Some(43.3531),
+                Some(-8.4053),
+            ),
+            Stop::new(
+                1,
+                "Cruce, \"O Rego\"".to_string(),
+                "Cruce\tO Rego".to_string(),
+                2,
+                None,
+                None,
+                None,
+                None,
+            ),
+        ]
+    }
+
+    fn written(format: Format) -> String {
+        let mut out = Vec::new();
+        write_records(&mut out, format, &stops()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_text_and_table() {
+        assert_eq!(
+            written(Format::Text),
+            "5274\tEstación de Coruña (A CORUÑA)\n1\tCruce, \"O Rego\"\n"
+        );
+        assert_eq!(
+            written(Format::Table),
+            "Id    Name\n5274  Estación de Coruña (A CORUÑA)\n1     Cruce, \"O Rego\"\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_and_tsv() {
+        let csv = written(Format::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "id,name,web_name,weight,latitude,longitude");
+        assert_eq!(
+            lines[1],
+            "5274,Estación de Coruña (A CORUÑA),Estación de Coruña,516,43.3531,-8.4053"
+        );
+        assert_eq!(lines[2], "1,\"Cruce, \"\"O Rego\"\"\",Cruce\tO Rego,2,,");
+
+        let tsv = written(Format::Tsv);
+        assert_eq!(
+            tsv.lines().nth(2),
+            Some("1\tCruce, \"O Rego\"\tCruce O Rego\t2\t\t")
+        );
+    }
+
+    #[test]
+    fn test_json_and_ndjson() {
+        let json: Value = serde_json::from_str(&written(Format::Json)).unwrap();
+        assert_eq!(json[0]["id"], 5274);
+        assert_eq!(json[1]["latitude"], Value::Null);
+
+        let ndjson = written(Format::Ndjson);
+        let lines: Vec<Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["web_name"], "Cruce\tO Rego");
+
+        let mut out = Vec::new();
+        write_records::<Stop>(&mut out, Format::Json, &[]).unwrap();
+        assert_eq!(out, b"[]\n");
+    }
+}
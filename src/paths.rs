@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+const APP_DIR: &str = "arriva-tui";
+const CONFIG_FILE: &str = "config.toml";
+const FAVOURITES_FILE: &str = "favourites.json";
+const HISTORY_FILE: &str = "history.json";
+const STOPS_CACHE_FILE: &str = "stops.json";
+
+/// `$XDG_CONFIG_HOME/arriva-tui`, or `~/.config/arriva-tui` when it is not set
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+pub fn favourites_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(FAVOURITES_FILE))
+}
+
+/// `$XDG_DATA_HOME/arriva-tui`, or `~/.local/share/arriva-tui` when it is not set
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// `$XDG_CACHE_HOME/arriva-tui`, or `~/.cache/arriva-tui` when it is not set
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn stops_cache_path() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join(STOPS_CACHE_FILE))
+}
@@ -1,12 +1,24 @@
-use std::path::Display;
-
+use super::money::DecimalSeparator;
+use super::money::Fare;
 use super::stops::Stop;
 use crate::prelude::*;
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta, TimeZone,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 use serde_json::Value;
 
 const OUTWARD_STR: &str = "ida";
 const RETURN_STR: &str = "vuelta";
 
+/// Date format expected by the goSearch endpoint
+pub const DATE_FORMAT: &str = "%d-%m-%Y";
+
+/// Parses a DD-MM-YYYY date, rejecting days that do not exist
+pub fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, DATE_FORMAT).map_err(|_| Error::InvalidDate(date.to_string()))
+}
+
 #[derive(Debug, Clone)]
 pub struct ExpeditionRequest {
     from: usize,
@@ -38,73 +50,120 @@ impl ExpeditionRequest {
 #[derive(Clone)]
 pub struct Expedition {
     name: String,
-    departure: String,
-    arrival: String,
-    cost: String,
+    departure: DateTime<FixedOffset>,
+    arrival: DateTime<FixedOffset>,
+    fare: Fare,
+    // every field Arriva sent for this expedition, for the detail view
+    raw: Value,
 }
 
 impl Expedition {
-    pub fn new(name: String, departure: String, arrival: String, cost: String) -> Self {
+    pub fn new(
+        name: String,
+        departure: DateTime<FixedOffset>,
+        mut arrival: DateTime<FixedOffset>,
+        fare: Fare,
+    ) -> Self {
+        // an arrival after midnight may come stamped with the departure day
+        if arrival < departure {
+            if let Some(next_day) = arrival.checked_add_days(Days::new(1)) {
+                arrival = next_day;
+            }
+        }
+
         Self {
             name,
             departure,
             arrival,
-            cost,
+            fare,
+            raw: Value::Null,
         }
     }
 
-    pub fn from(expedition_value: &Value) -> Result<Self> {
+    /// Builds the expedition at `index` of the `direction` array of a goSearch response
+    pub fn from(expedition_value: &Value, direction: &'static str, index: usize) -> Result<Self> {
+        let missing = |field: &'static str| Error::MissingExpeditionField {
+            direction,
+            index,
+            field,
+        };
+
         let name = expedition_value["Descripcion_Web"]
             .as_str()
-            .ok_or_else(|| Error::Generic("Failed to get expedition name".to_string()))?
+            .ok_or_else(|| missing("Descripcion_Web"))?
             .to_string();
         let departure_value = expedition_value["hora_salida"]
             .as_str()
-            .ok_or_else(|| Error::Generic("Failed to get departure value".to_string()))?
+            .ok_or_else(|| missing("hora_salida"))?
             .to_string();
         let arrival_value = expedition_value["hora_llegada"]
             .as_str()
-            .ok_or_else(|| Error::Generic("Failed to get arrival value".to_string()))?
+            .ok_or_else(|| missing("hora_llegada"))?
             .to_string();
-        let cost = expedition_value["tarifa_basica"]
-            .as_u64()
-            .ok_or_else(|| Error::Generic("Failed to get cost value".to_string()))?;
-
-        // Hora_Salida and Hora_Llegada are in the format "YYYY-MM-DDTHH:MM:00+02:00"
-        // and we want to extract "HH:MM"
-
-        let departure = departure_value.split("T").collect::<Vec<&str>>()[1] // "HH:MM:00+02:00"
-            .split(":")
-            .collect::<Vec<&str>>()[0..2]
-            .join(":"); // "HH:MM"
+        let fare = Fare::from_value(expedition_value).ok_or_else(|| missing("tarifa_basica"))?;
 
-        let arrival = arrival_value.split("T").collect::<Vec<&str>>()[1] // "HH:MM:00+02:00"
-            .split(":")
-            .collect::<Vec<&str>>()[0..2]
-            .join(":"); // "HH:MM"
+        let (departure, departure_offset) =
+            parse_timestamp(&departure_value).ok_or_else(|| missing("hora_salida"))?;
+        let (arrival, arrival_offset) =
+            parse_timestamp(&arrival_value).ok_or_else(|| missing("hora_llegada"))?;
+        // a timestamp without offset takes the one of the other, or Spanish time
+        let departure = with_offset(departure, departure_offset.or(arrival_offset))
+            .ok_or_else(|| missing("hora_salida"))?;
+        let arrival = with_offset(arrival, arrival_offset.or(departure_offset))
+            .ok_or_else(|| missing("hora_llegada"))?;
 
-        Ok(Self::new(
-            name,
-            departure,
-            arrival,
-            format!("{:.2}", cost as f64 / 100.0),
-        ))
+        let mut expedition = Self::new(name, departure, arrival, fare);
+        // still before the departure after moving it to the next day
+        if expedition.arrival < expedition.departure {
+            return Err(missing("hora_llegada"));
+        }
+        expedition.raw = expedition_value.clone();
+        Ok(expedition)
     }
 
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
 
-    pub fn get_departure(&self) -> String {
-        self.departure.clone()
+    pub fn get_departure(&self) -> DateTime<FixedOffset> {
+        self.departure
+    }
+
+    pub fn get_arrival(&self) -> DateTime<FixedOffset> {
+        self.arrival
+    }
+
+    pub fn get_duration(&self) -> TimeDelta {
+        self.arrival - self.departure
+    }
+
+    /// Whether the bus arrives on a later day than it leaves
+    pub fn arrives_next_day(&self) -> bool {
+        self.arrival.date_naive() > self.departure.date_naive()
     }
 
-    pub fn get_arrival(&self) -> String {
-        self.arrival.clone()
+    pub fn get_fare(&self) -> &Fare {
+        &self.fare
     }
 
-    pub fn get_cost(&self) -> String {
-        self.cost.clone()
+    /// The expedition exactly as the goSearch response returned it
+    pub fn get_raw(&self) -> &Value {
+        &self.raw
+    }
+}
+
+// The output schema: RFC 3339 times, the duration they give and the fares, leaving
+// out the raw response
+impl Serialize for Expedition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut expedition = serializer.serialize_struct("Expedition", 6)?;
+        expedition.serialize_field("name", &self.name)?;
+        expedition.serialize_field("departure", &self.departure)?;
+        expedition.serialize_field("arrival", &self.arrival)?;
+        expedition.serialize_field("duration_minutes", &self.get_duration().num_minutes())?;
+        expedition.serialize_field("arrives_next_day", &self.arrives_next_day())?;
+        expedition.serialize_field("fare", &self.fare)?;
+        expedition.end()
     }
 }
 
@@ -113,7 +172,10 @@ impl std::fmt::Debug for Expedition {
         write!(
             f,
             "{}: {} - {} - {}",
-            self.name, self.departure, self.arrival, self.cost
+            self.name,
+            self.departure.to_rfc3339(),
+            self.arrival.to_rfc3339(),
+            self.fare.basic
         )
     }
 }
@@ -122,77 +184,100 @@ impl std::fmt::Display for Expedition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "\nLínea:     {}\nHorario:   {} -> {}\nCoste(€):  {}",
-            self.name, self.departure, self.arrival, self.cost
+            "\nLínea:     {}\nHorario:   {} -> {} ({})\nCoste(€):  {}",
+            self.name,
+            self.departure.format("%d-%m-%Y %H:%M"),
+            self.arrival.format("%d-%m-%Y %H:%M"),
+            format_duration(self.get_duration()),
+            self.fare.basic.format_amount(DecimalSeparator::Point)
         )
     }
 }
 
-pub fn deserialize_expeditions(value: Value) -> Result<(Vec<Expedition>, Vec<Expedition>)> {
-    let mut expedition_errors: (bool, bool) = (false, false);
-
-    let outward_expeditions_value = match value["expediciones"][OUTWARD_STR].as_array() {
-        Some(expeditions) => expeditions,
-        None => {
-            return Err(Error::Generic(
-                "Failed to parse outward expeditions".to_string(),
-            ))
-        }
-    };
+// Hora_Salida and Hora_Llegada are in the format "YYYY-MM-DDTHH:MM:00+02:00",
+// timestamps missing seconds or the offset are accepted, the offset is then `None`
+fn parse_timestamp(value: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z"))
+    {
+        return Some((timestamp.naive_local(), Some(*timestamp.offset())));
+    }
 
-    let return_expeditions_value = match value["expediciones"][RETURN_STR].as_array() {
-        Some(expeditions) => expeditions,
-        None => {
-            return Err(Error::Generic(
-                "Failed to parse return expeditions".to_string(),
-            ))
-        }
-    };
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .map(|naive| (naive, None))
+}
 
-    let outward_expeditions: Vec<Expedition> = outward_expeditions_value
-        .iter()
-        .map(|expedition| match Expedition::from(expedition) {
-            Ok(expedition) => expedition,
-            Err(err) => {
-                expedition_errors.1 = true;
-                Expedition::new(
-                    "Error".to_string(),
-                    "00:00".to_string(),
-                    "00:00".to_string(),
-                    "0.00".to_string(),
-                )
-            }
+// the wall time at `offset`, or in Spanish peninsular time without one
+fn with_offset(naive: NaiveDateTime, offset: Option<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    offset
+        .unwrap_or_else(|| madrid_offset(naive))
+        .from_local_datetime(&naive)
+        .single()
+}
+
+// Europe/Madrid is UTC+2 from the last Sunday of March to the last Sunday of
+// October, changing at 01:00 UTC, and UTC+1 the rest of the year
+fn madrid_offset(naive: NaiveDateTime) -> FixedOffset {
+    let last_sunday = |month| {
+        NaiveDate::from_ymd_opt(naive.year(), month, 31).map(|last_day| {
+            let back = last_day.weekday().num_days_from_sunday();
+            (last_day - Days::new(back.into())).and_hms_opt(1, 0, 0)
         })
-        .collect();
+    };
+    let utc = naive - TimeDelta::hours(1);
+    let summer = match (last_sunday(3).flatten(), last_sunday(10).flatten()) {
+        (Some(start), Some(end)) => start <= utc && utc < end,
+        _ => false,
+    };
+    let hours = if summer { 2 } else { 1 };
+    FixedOffset::east_opt(hours * 3600).expect("offset within a day")
+}
 
-    let return_expeditions = return_expeditions_value
+/// Arrival time, marked when the bus gets there on the next day
+pub fn format_arrival(expedition: &Expedition) -> String {
+    let arrival = expedition.get_arrival().format("%H:%M").to_string();
+    if expedition.arrives_next_day() {
+        format!("{} (+1)", arrival)
+    } else {
+        arrival
+    }
+}
+
+/// Formats a trip duration as "35m" or "1h 05m"
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes();
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
+
+fn deserialize_direction(value: &Value, direction: &'static str) -> Result<Vec<Expedition>> {
+    value["expediciones"][direction]
+        .as_array()
+        .ok_or(Error::MalformedExpeditions(direction))?
         .iter()
-        .map(|expedition| match Expedition::from(expedition) {
-            Ok(expedition) => expedition,
-            Err(err) => {
-                expedition_errors.1 = true;
-                Expedition::new(
-                    "Error".to_string(),
-                    "00:00".to_string(),
-                    "00:00".to_string(),
-                    "0.00".to_string(),
-                )
-            }
-        })
-        .collect();
+        .enumerate()
+        .map(|(index, expedition)| Expedition::from(expedition, direction, index))
+        .collect()
+}
+
+pub fn deserialize_expeditions(value: Value) -> Result<(Vec<Expedition>, Vec<Expedition>)> {
+    let outward_expeditions = deserialize_direction(&value, OUTWARD_STR)?;
+    let return_expeditions = deserialize_direction(&value, RETURN_STR)?;
 
-    match expedition_errors {
-        (false, false) => Ok((outward_expeditions, return_expeditions)),
-        (true, false) => Err(Error::Generic(
-            "Failed to deserialize some outward expeditions".to_string(),
-        )),
-        (false, true) => Err(Error::Generic(
-            "Failed to deserialize some return expeditions".to_string(),
-        )),
-        _ => Err(Error::Generic(
-            "Failed to deserialize both expedition arrays".to_string(),
-        )),
+    if outward_expeditions.is_empty() && return_expeditions.is_empty() {
+        return Err(Error::EmptyResults("expeditions"));
     }
+    Ok((outward_expeditions, return_expeditions))
 }
 
 #[cfg(test)]
@@ -243,4 +328,190 @@ mod tests {
         let expedition = ExpeditionRequest::new(1, 2, String::from("2021-01-01"));
         assert_eq!(expedition.get_payload(), "controller=buses&method=goSearch&data%5Bfrom%5D=1&data%5Bto%5D=2&data%5Bdate%5D=2021-01-01");
     }
+
+    #[test]
+    fn test_deserialize_expeditions_missing_field() {
+        let value = serde_json::json!({
+            "expediciones": {
+                "ida": [
+                    {"Descripcion_Web": "A", "hora_salida": "2024-05-20T07:30:00+02:00", "hora_llegada": "2024-05-20T08:05:00+02:00", "tarifa_basica": 235},
+                    {"Descripcion_Web": "B", "hora_salida": "2024-05-20T09:30:00+02:00", "tarifa_basica": 235}
+                ],
+                "vuelta": []
+            }
+        });
+
+        match deserialize_expeditions(value) {
+            Err(Error::MissingExpeditionField {
+                direction,
+                index,
+                field,
+            }) => {
+                assert_eq!(direction, OUTWARD_STR);
+                assert_eq!(index, 1);
+                assert_eq!(field, "hora_llegada");
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_expeditions_malformed() {
+        let value = serde_json::json!({ "expediciones": { "ida": [] } });
+        assert!(matches!(
+            deserialize_expeditions(value),
+            Err(Error::MalformedExpeditions(RETURN_STR))
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_expeditions_empty() {
+        let value = serde_json::json!({ "expediciones": { "ida": [], "vuelta": [] } });
+        assert!(matches!(
+            deserialize_expeditions(value),
+            Err(Error::EmptyResults("expeditions"))
+        ));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("20-05-2024").unwrap(),
+            NaiveDate::from_ymd_opt(2024, 5, 20).unwrap()
+        );
+        assert!(matches!(
+            parse_date("31-02-2024"),
+            Err(Error::InvalidDate(_))
+        ));
+        assert!(matches!(
+            parse_date("2024-05-20"),
+            Err(Error::InvalidDate(_))
+        ));
+    }
+
+    fn expedition_value(departure: &str, arrival: &str) -> Value {
+        serde_json::json!({
+            "Descripcion_Web": "A Coruña - Laracha",
+            "hora_salida": departure,
+            "hora_llegada": arrival,
+            "tarifa_basica": 235
+        })
+    }
+
+    #[test]
+    fn test_expedition_timestamps() {
+        let value = expedition_value("2024-05-20T07:30:00+02:00", "2024-05-20T08:05:00+02:00");
+        let expedition = Expedition::from(&value, OUTWARD_STR, 0).unwrap();
+
+        assert_eq!(
+            expedition.get_departure(),
+            DateTime::parse_from_rfc3339("2024-05-20T07:30:00+02:00").unwrap()
+        );
+        assert_eq!(expedition.get_departure().offset().local_minus_utc(), 7200);
+        assert_eq!(expedition.get_duration(), TimeDelta::minutes(35));
+        assert!(!expedition.arrives_next_day());
+        assert_eq!(expedition.get_fare().basic.get_cents(), 235);
+        assert_eq!(expedition.get_raw(), &value);
+    }
+
+    #[test]
+    fn test_serialize_schema() {
+        let mut value = expedition_value("2024-05-20T23:40:00+02:00", "2024-05-21T00:25:00+02:00");
+        value["tarifa_bono"] = serde_json::json!(165);
+        let expedition = Expedition::from(&value, OUTWARD_STR, 0).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&expedition).unwrap(),
+            serde_json::json!({
+                "name": "A Coruña - Laracha",
+                "departure": "2024-05-20T23:40:00+02:00",
+                "arrival": "2024-05-21T00:25:00+02:00",
+                "duration_minutes": 45,
+                "arrives_next_day": true,
+                "fare": {
+                    "basic": { "cents": 235, "currency": "EUR" },
+                    "tariffs": { "tarifa_bono": { "cents": 165, "currency": "EUR" } }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_expedition_arrival_after_midnight() {
+        let value = expedition_value("2024-05-20T23:40:00+02:00", "2024-05-21T00:25:00+02:00");
+        let expedition = Expedition::from(&value, RETURN_STR, 0).unwrap();
+        assert_eq!(expedition.get_duration(), TimeDelta::minutes(45));
+        assert!(expedition.arrives_next_day());
+
+        // same trip with the arrival stamped on the departure day
+        let value = expedition_value("2024-05-20T23:40:00+02:00", "2024-05-20T00:25:00+02:00");
+        let expedition = Expedition::from(&value, RETURN_STR, 0).unwrap();
+        assert_eq!(expedition.get_duration(), TimeDelta::minutes(45));
+        assert!(expedition.arrives_next_day());
+    }
+
+    #[test]
+    fn test_expedition_lenient_timestamps() {
+        let value = expedition_value("2024-05-20T07:30+02:00", "2024-05-20 08:05:00");
+        let expedition = Expedition::from(&value, OUTWARD_STR, 0).unwrap();
+        assert_eq!(
+            expedition.get_departure().format("%H:%M").to_string(),
+            "07:30"
+        );
+        // the arrival takes the offset of the departure
+        assert_eq!(
+            expedition.get_arrival().to_rfc3339(),
+            "2024-05-20T08:05:00+02:00"
+        );
+    }
+
+    #[test]
+    fn test_expedition_spanish_time_without_offsets() {
+        let value = expedition_value("2024-01-15 07:30", "2024-01-15 08:05");
+        let expedition = Expedition::from(&value, OUTWARD_STR, 0).unwrap();
+        assert_eq!(
+            expedition.get_departure().to_rfc3339(),
+            "2024-01-15T07:30:00+01:00"
+        );
+
+        let value = expedition_value("2024-07-15 07:30", "2024-07-15 08:05");
+        let expedition = Expedition::from(&value, OUTWARD_STR, 0).unwrap();
+        assert_eq!(
+            expedition.get_arrival().to_rfc3339(),
+            "2024-07-15T08:05:00+02:00"
+        );
+        assert_eq!(expedition.get_duration(), TimeDelta::minutes(35));
+    }
+
+    #[test]
+    fn test_expedition_arrival_days_before() {
+        let value = expedition_value("2024-05-20T23:40:00+02:00", "2024-05-18T00:25:00+02:00");
+        assert!(matches!(
+            Expedition::from(&value, RETURN_STR, 2),
+            Err(Error::MissingExpeditionField {
+                field: "hora_llegada",
+                index: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_expedition_invalid_timestamp() {
+        let value = expedition_value("07:30", "2024-05-20T08:05:00+02:00");
+        assert!(matches!(
+            Expedition::from(&value, OUTWARD_STR, 3),
+            Err(Error::MissingExpeditionField {
+                field: "hora_salida",
+                index: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(TimeDelta::minutes(35)), "35m");
+        assert_eq!(format_duration(TimeDelta::minutes(65)), "1h 05m");
+    }
 }
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::utils::json_file::{load_json, save_json};
+
+use super::Route;
+
+/// The routes starred by the user, kept as pretty printed JSON in the config directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Favourites {
+    pub routes: Vec<Route>,
+}
+
+impl Favourites {
+    /// Reads the favourites file, a missing file just means nothing was starred yet.
+    /// An unreadable or corrupt one is only logged, the app starts without favourites
+    pub fn load(path: &Path) -> Self {
+        load_json(path).unwrap_or_else(|err| {
+            tracing::warn!(path = %path.display(), error = %err, "ignoring the favourites");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        save_json(path, self)
+    }
+
+    pub fn contains(&self, route: &Route) -> bool {
+        self.routes
+            .iter()
+            .any(|r| r.from == route.from && r.to == route.to)
+    }
+
+    /// Stars the route, or unstars it if it already was. Returns whether it is starred now.
+    pub fn toggle(&mut self, route: Route) -> bool {
+        if self.contains(&route) {
+            self.routes
+                .retain(|r| !(r.from == route.from && r.to == route.to));
+            false
+        } else {
+            self.routes.push(route);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::temp_dir::TempDir;
+
+    fn route(from: usize, to: usize) -> Route {
+        Route {
+            from,
+            from_name: from.to_string(),
+            to,
+            to_name: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut favourites = Favourites::default();
+        assert!(favourites.toggle(route(5274, 5714)));
+        assert!(favourites.toggle(route(5714, 5274)));
+        assert!(favourites.contains(&route(5274, 5714)));
+
+        assert!(!favourites.toggle(route(5274, 5714)));
+        assert_eq!(favourites.routes, vec![route(5714, 5274)]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new("favourites");
+        let path = dir.join("favourites.json");
+        assert_eq!(Favourites::load(&path), Favourites::default());
+
+        let favourites = Favourites {
+            routes: vec![route(5274, 5714)],
+        };
+        favourites.save(&path).unwrap();
+        assert_eq!(Favourites::load(&path), favourites);
+    }
+
+    #[test]
+    fn test_load_corrupt_file() {
+        let dir = TempDir::new("favourites");
+        let path = dir.join("favourites.json");
+        std::fs::write(&path, "{\"routes\": [{\"from\": 52").unwrap();
+        assert_eq!(Favourites::load(&path), Favourites::default());
+    }
+}
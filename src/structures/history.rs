@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::utils::json_file::{load_json, save_json};
+
+use super::Route;
+
+/// How many searches are remembered
+pub const HISTORY_LIMIT: usize = 20;
+
+/// A search for expeditions of a route on a date, and when it was made
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    #[serde(flatten)]
+    pub route: Route,
+    pub date: NaiveDate,
+    pub searched_at: DateTime<Local>,
+}
+
+impl HistoryEntry {
+    pub fn new(route: Route, date: NaiveDate) -> Self {
+        Self {
+            route,
+            date,
+            searched_at: Local::now(),
+        }
+    }
+
+    fn same_search(&self, other: &HistoryEntry) -> bool {
+        self.route.from == other.route.from
+            && self.route.to == other.route.to
+            && self.date == other.date
+    }
+}
+
+/// The latest searches, newest first, kept as pretty printed JSON in the data directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Reads the history file, a missing file just means nothing was searched yet.
+    /// An unreadable or corrupt one is only logged, the history starts over
+    pub fn load(path: &Path) -> Self {
+        load_json(path).unwrap_or_else(|err| {
+            tracing::warn!(path = %path.display(), error = %err, "ignoring the search history");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        save_json(path, self)
+    }
+
+    /// Puts `entry` first, dropping an older copy of the same search and anything past `limit`
+    pub fn record(&mut self, entry: HistoryEntry, limit: usize) {
+        self.entries.retain(|e| !e.same_search(&entry));
+        self.entries.insert(0, entry);
+        self.entries.truncate(limit);
+    }
+
+    pub fn remove(&mut self, entry: &HistoryEntry) {
+        self.entries.retain(|e| !e.same_search(entry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::temp_dir::TempDir;
+
+    fn entry(from: usize, to: usize, day: u32) -> HistoryEntry {
+        let route = Route {
+            from,
+            from_name: from.to_string(),
+            to,
+            to_name: to.to_string(),
+        };
+        HistoryEntry::new(route, NaiveDate::from_ymd_opt(2024, 5, day).unwrap())
+    }
+
+    fn searches(history: &History) -> Vec<(usize, u32)> {
+        use chrono::Datelike;
+        history
+            .entries
+            .iter()
+            .map(|e| (e.route.from, e.date.day()))
+            .collect()
+    }
+
+    #[test]
+    fn test_record_newest_first_without_duplicates() {
+        let mut history = History::default();
+        history.record(entry(1, 2, 20), 3);
+        history.record(entry(3, 4, 20), 3);
+        history.record(entry(1, 2, 20), 3);
+        assert_eq!(searches(&history), vec![(1, 20), (3, 20)]);
+
+        // the same route on another day is another search
+        history.record(entry(1, 2, 21), 3);
+        history.record(entry(5, 6, 20), 3);
+        assert_eq!(searches(&history), vec![(5, 20), (1, 21), (1, 20)]);
+
+        history.remove(&entry(1, 21, 21));
+        assert_eq!(history.entries.len(), 3);
+        history.remove(&entry(1, 2, 21));
+        assert_eq!(searches(&history), vec![(5, 20), (1, 20)]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new("history");
+        let path = dir.join("history.json");
+
+        let mut history = History::default();
+        history.record(entry(5274, 5714, 20), HISTORY_LIMIT);
+        history.save(&path).unwrap();
+        assert_eq!(History::load(&path), history);
+    }
+
+    #[test]
+    fn test_load_corrupt_file() {
+        let dir = TempDir::new("history");
+        let path = dir.join("history.json");
+        std::fs::write(&path, "[{\"from\": 52").unwrap();
+        assert_eq!(History::load(&path), History::default());
+    }
+}
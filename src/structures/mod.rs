@@ -1,6 +1,19 @@
 pub mod expeditions;
+pub mod favourites;
+pub mod history;
+pub mod money;
+pub mod routes;
+pub mod stop_cache;
 pub mod stops;
 
-pub use expeditions::{deserialize_expeditions, Expedition, ExpeditionRequest};
+pub use expeditions::{
+    deserialize_expeditions, format_arrival, format_duration, parse_date, Expedition,
+    ExpeditionRequest, DATE_FORMAT,
+};
+pub use favourites::Favourites;
+pub use history::{History, HistoryEntry, HISTORY_LIMIT};
+pub use money::{Currency, DecimalSeparator, Fare, Money};
+pub use routes::Route;
 pub use serde_json::Value;
+pub use stop_cache::StopCache;
 pub use stops::{deserialize_stops, Stop};
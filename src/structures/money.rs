@@ -0,0 +1,247 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+const TARIFF_PREFIX: &str = "tarifa_";
+const BASIC_TARIFF: &str = "tarifa_basica";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Currency {
+    #[default]
+    #[serde(rename = "EUR")]
+    Eur,
+}
+
+impl Currency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Eur => "EUR",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Eur => "€",
+        }
+    }
+}
+
+/// Character between the units and the cents when showing an amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecimalSeparator {
+    #[default]
+    Point,
+    Comma,
+}
+
+impl DecimalSeparator {
+    /// Spanish and Galician locales write "2,35", everything else "2.35"
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+
+        if locale.starts_with("es") || locale.starts_with("gl") {
+            DecimalSeparator::Comma
+        } else {
+            DecimalSeparator::Point
+        }
+    }
+
+    fn as_char(&self) -> char {
+        match self {
+            DecimalSeparator::Point => '.',
+            DecimalSeparator::Comma => ',',
+        }
+    }
+}
+
+/// An amount of money stored in cents, so it can be summed and compared exactly.
+/// Serialized as `{"cents": 235, "currency": "EUR"}`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Money {
+    cents: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(cents: i64, currency: Currency) -> Self {
+        Self { cents, currency }
+    }
+
+    pub fn eur(cents: i64) -> Self {
+        Self::new(cents, Currency::Eur)
+    }
+
+    /// Reads an amount in cents from a JSON number or numeric string
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let cents = match value {
+            Value::Number(number) => number.as_i64()?,
+            Value::String(text) => text.trim().parse().ok()?,
+            _ => return None,
+        };
+        Some(Self::eur(cents))
+    }
+
+    pub fn get_cents(&self) -> i64 {
+        self.cents
+    }
+
+    pub fn get_currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// The sum of both amounts, `None` when their currencies differ or it overflows
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        (self.currency == other.currency)
+            .then(|| self.cents.checked_add(other.cents))
+            .flatten()
+            .map(|cents| Money::new(cents, self.currency))
+    }
+
+    /// The difference of both amounts, `None` when their currencies differ or it overflows
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        (self.currency == other.currency)
+            .then(|| self.cents.checked_sub(other.cents))
+            .flatten()
+            .map(|cents| Money::new(cents, self.currency))
+    }
+
+    /// The sum of every amount, zero euros when there are none and `None` when
+    /// their currencies differ or it overflows
+    pub fn checked_sum<'a>(amounts: impl IntoIterator<Item = &'a Money>) -> Option<Money> {
+        let mut amounts = amounts.into_iter().copied();
+        let first = amounts.next().unwrap_or_default();
+        amounts.try_fold(first, Money::checked_add)
+    }
+
+    /// The amount without currency, as "2.35" or "2,35"
+    pub fn format_amount(&self, separator: DecimalSeparator) -> String {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let cents = self.cents.unsigned_abs();
+        format!(
+            "{}{}{}{:02}",
+            sign,
+            cents / 100,
+            separator.as_char(),
+            cents % 100
+        )
+    }
+
+    /// The amount followed by the currency symbol, as "2,35 €"
+    pub fn format(&self, separator: DecimalSeparator) -> String {
+        format!(
+            "{} {}",
+            self.format_amount(separator),
+            self.currency.symbol()
+        )
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(DecimalSeparator::Point))
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.currency == other.currency).then(|| self.cents.cmp(&other.cents))
+    }
+}
+
+/// Price of an expedition: the basic fare plus any other tariff Arriva sends along
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct Fare {
+    pub basic: Money,
+    pub tariffs: BTreeMap<String, Money>,
+}
+
+impl Fare {
+    pub fn new(basic: Money) -> Self {
+        Self {
+            basic,
+            tariffs: BTreeMap::new(),
+        }
+    }
+
+    /// Reads `tarifa_basica` and every other `tarifa_*` amount of an expedition,
+    /// returns `None` when the basic fare is missing
+    pub fn from_value(expedition_value: &Value) -> Option<Self> {
+        let basic = Money::from_value(&expedition_value[BASIC_TARIFF])?;
+        let tariffs = expedition_value
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(key, _)| key.starts_with(TARIFF_PREFIX) && key.as_str() != BASIC_TARIFF)
+            .filter_map(|(key, value)| Some((key.clone(), Money::from_value(value)?)))
+            .collect();
+
+        Some(Self { basic, tariffs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_with_separator() {
+        let money = Money::eur(235);
+        assert_eq!(money.format_amount(DecimalSeparator::Point), "2.35");
+        assert_eq!(money.format(DecimalSeparator::Comma), "2,35 €");
+        assert_eq!(
+            Money::eur(-5).format_amount(DecimalSeparator::Comma),
+            "-0,05"
+        );
+        assert_eq!(money.to_string(), "2.35 €");
+    }
+
+    #[test]
+    fn test_arithmetic_and_ordering() {
+        let fares = [Money::eur(235), Money::eur(165), Money::eur(255)];
+        assert_eq!(Money::checked_sum(&fares), Some(Money::eur(655)));
+        assert_eq!(Money::checked_sum(&[]), Some(Money::eur(0)));
+        assert_eq!(
+            Money::eur(255).checked_sub(Money::eur(235)),
+            Some(Money::eur(20))
+        );
+        assert_eq!(Money::eur(i64::MAX).checked_add(Money::eur(1)), None);
+        assert!(Money::eur(165) < Money::eur(235));
+        assert_eq!(
+            fares
+                .iter()
+                .copied()
+                .reduce(|a, b| if b < a { b } else { a }),
+            Some(Money::eur(165))
+        );
+    }
+
+    #[test]
+    fn test_fare_keeps_other_tariffs() {
+        let value = serde_json::json!({
+            "tarifa_basica": 235,
+            "tarifa_bono": "165",
+            "tarifa_nombre": "not an amount",
+            "hora_salida": "2024-05-20T07:30:00+02:00"
+        });
+
+        let fare = Fare::from_value(&value).unwrap();
+        assert_eq!(fare.basic, Money::eur(235));
+        assert_eq!(fare.tariffs.len(), 1);
+        assert_eq!(fare.tariffs["tarifa_bono"], Money::eur(165));
+    }
+
+    #[test]
+    fn test_fare_without_basic_tariff() {
+        let value = serde_json::json!({ "tarifa_bono": 165 });
+        assert!(Fare::from_value(&value).is_none());
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use super::Stop;
+
+/// A From/To pair of stops, stored by id with the names they had when it was saved
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub from: usize,
+    pub from_name: String,
+    pub to: usize,
+    pub to_name: String,
+}
+
+impl Route {
+    pub fn new(from: &Stop, to: &Stop) -> Self {
+        Self {
+            from: from.get_parada(),
+            from_name: from.get_nombre(),
+            to: to.get_parada(),
+            to_name: to.get_nombre(),
+        }
+    }
+
+    /// Looks both stops up in the current catalogue, `None` if any is no longer served
+    pub fn resolve(&self, stops: &[Stop]) -> Option<(Stop, Stop)> {
+        let find = |parada: usize| stops.iter().find(|stop| stop.get_parada() == parada);
+        Some((find(self.from)?.clone(), find(self.to)?.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(parada: usize, nombre: &str) -> Stop {
+        Stop::new(
+            parada,
+            nombre.to_string(),
+            nombre.to_string(),
+            1,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_resolve() {
+        let stops = vec![stop(5274, "Coruña"), stop(5714, "Laracha")];
+        let route = Route::new(&stops[1], &stops[0]);
+
+        let (from, to) = route.resolve(&stops).unwrap();
+        assert_eq!(from.get_parada(), 5714);
+        assert_eq!(to.get_parada(), 5274);
+
+        assert!(route.resolve(&stops[..1]).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::utils::json_file::{load_json, save_json};
+use crate::utils::Validators;
+
+use super::Stop;
+
+/// The stop catalogue as it was last fetched, with when and which version it was,
+/// kept as JSON in the cache directory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StopCache {
+    pub fetched_at: DateTime<Local>,
+    #[serde(flatten)]
+    pub validators: Validators,
+    #[serde(serialize_with = "super::stops::serialize_as_arriva")]
+    pub stops: Vec<Stop>,
+}
+
+impl StopCache {
+    pub fn new(stops: Vec<Stop>, validators: Validators) -> Self {
+        Self {
+            fetched_at: Local::now(),
+            validators,
+            stops,
+        }
+    }
+
+    /// Reads the cache file, `None` when it is missing, unreadable or has no stops,
+    /// so they are fetched again
+    pub fn load(path: &Path) -> Option<Self> {
+        load_json::<Option<Self>>(path)
+            .ok()
+            .flatten()
+            .filter(|cache| !cache.stops.is_empty())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        save_json(path, self)
+    }
+
+    /// Saves it, only logging a failure: the stops are then fetched again next time
+    pub fn save_or_warn(&self, path: &Path) {
+        if let Err(err) = self.save(path) {
+            tracing::warn!(path = %path.display(), error = %err, "could not cache the stops");
+        }
+    }
+
+    pub fn age(&self, now: DateTime<Local>) -> TimeDelta {
+        now - self.fetched_at
+    }
+
+    /// Whether it was fetched longer than `ttl` ago
+    pub fn is_stale(&self, ttl: TimeDelta, now: DateTime<Local>) -> bool {
+        self.age(now) >= ttl
+    }
+
+    /// The same stops, checked to be current just now
+    pub fn touched(self) -> Self {
+        Self {
+            fetched_at: Local::now(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::temp_dir::TempDir;
+
+    fn cache() -> StopCache {
+        let stop = Stop::new(
+            5714,
+            "Laracha (LARACHA)".into(),
+            "Laracha".into(),
+            121,
+            Some(43.25),
+            Some(-8.59),
+            Some(43.2492),
+            Some(-8.5872),
+        );
+        StopCache::new(
+            vec![stop],
+            Validators {
+                etag: Some("\"abc\"".to_string()),
+                last_modified: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new("stops");
+        let path = dir.join("stops.json");
+        assert_eq!(StopCache::load(&path), None);
+
+        let cache = cache();
+        cache.save(&path).unwrap();
+        assert_eq!(StopCache::load(&path), Some(cache));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(StopCache::load(&path), None);
+    }
+
+    #[test]
+    fn test_staleness() {
+        let cache = cache();
+        let ttl = TimeDelta::hours(24);
+        assert!(!cache.is_stale(ttl, cache.fetched_at + TimeDelta::hours(23)));
+        assert!(cache.is_stale(ttl, cache.fetched_at + TimeDelta::hours(24)));
+        assert_eq!(
+            cache.age(cache.fetched_at + TimeDelta::minutes(5)),
+            TimeDelta::minutes(5)
+        );
+    }
+}
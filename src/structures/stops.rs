@@ -1,5 +1,5 @@
-use serde::Deserialize;
-use serde_json::Error;
+use crate::prelude::*;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use std::fmt::{self, Debug, Display, Formatter};
 
 #[derive(Deserialize, Clone, PartialEq)]
@@ -21,6 +21,7 @@ pub struct Stop {
 }
 
 impl Stop {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         parada: usize,
         nombre: String,
@@ -50,6 +51,68 @@ impl Stop {
     pub fn get_nombre(&self) -> String {
         self.nombre.clone()
     }
+
+    pub fn get_nom_web(&self) -> String {
+        self.nom_web.clone()
+    }
+
+    pub fn get_peso(&self) -> isize {
+        self.peso
+    }
+
+    /// `latitud`, or `lat` when Arriva only sent that one
+    pub fn get_latitude(&self) -> Option<f64> {
+        self.latitud.or(self.lat)
+    }
+
+    pub fn get_longitude(&self) -> Option<f64> {
+        self.longitud.or(self.lon)
+    }
+}
+
+// The output schema, in English and with one pair of coordinates, instead of
+// the names Arriva sends
+impl Serialize for Stop {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut stop = serializer.serialize_struct("Stop", 6)?;
+        stop.serialize_field("id", &self.parada)?;
+        stop.serialize_field("name", &self.nombre)?;
+        stop.serialize_field("web_name", &self.nom_web)?;
+        stop.serialize_field("weight", &self.peso)?;
+        stop.serialize_field("latitude", &self.get_latitude())?;
+        stop.serialize_field("longitude", &self.get_longitude())?;
+        stop.end()
+    }
+}
+
+/// Writes the stops with the names Arriva sends, so the stop cache reads them back
+/// exactly as they were fetched
+pub(super) fn serialize_as_arriva<S: Serializer>(
+    stops: &[Stop],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct ArrivaStop<'a> {
+        parada: usize,
+        nombre: &'a str,
+        nom_web: &'a str,
+        peso: isize,
+        lat: Option<f64>,
+        lon: Option<f64>,
+        latitud: Option<f64>,
+        longitud: Option<f64>,
+    }
+
+    serializer.collect_seq(stops.iter().map(|stop| ArrivaStop {
+        parada: stop.parada,
+        nombre: &stop.nombre,
+        nom_web: &stop.nom_web,
+        peso: stop.peso,
+        lat: stop.lat,
+        lon: stop.lon,
+        latitud: stop.latitud,
+        longitud: stop.longitud,
+    }))
 }
 
 impl Debug for Stop {
@@ -97,7 +160,110 @@ struct StopList {
     paradas: Vec<Stop>,
 }
 
-pub fn deserialize_stops(response: String) -> Result<Vec<Stop>, Error> {
-    let stop_list: StopList = serde_json::from_str(&response)?;
+pub fn deserialize_stops(response: String) -> Result<Vec<Stop>> {
+    let stop_list: StopList = serde_json::from_str(&response).map_err(Error::MalformedStops)?;
+
+    if stop_list.paradas.is_empty() {
+        return Err(Error::EmptyResults("stops"));
+    }
     Ok(stop_list.paradas)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop_named(escaped: &str) -> Stop {
+        let response = format!(
+            r#"{{"paradas":[{{"parada":1,"nombre":"{}","nom_web":"{}","peso":1}}]}}"#,
+            escaped, escaped
+        );
+        deserialize_stops(response).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_deserialize_common_accents() {
+        let stop = stop_named(r"Estaci\u00f3n de Coru\u00f1a (A CORU\u00d1A)");
+        assert_eq!(stop.get_nombre(), "Estación de Coruña (A CORUÑA)");
+    }
+
+    #[test]
+    fn test_deserialize_ordinal_indicators() {
+        let stop = stop_named(r"R\u00faa 1\u00aa - Praza 2\u00ba");
+        assert_eq!(stop.get_nombre(), "Rúa 1ª - Praza 2º");
+    }
+
+    #[test]
+    fn test_deserialize_currency_and_apostrophes() {
+        let stop = stop_named(r"Cruce d\u2019Abaixo \u20ac");
+        assert_eq!(stop.get_nombre(), "Cruce d’Abaixo €");
+    }
+
+    #[test]
+    fn test_deserialize_uncommon_latin_letters() {
+        let stop =
+            stop_named(r"\u00c0 \u00e0 \u00e2 \u00ea \u00ef \u00f4 \u00e8 \u00ec \u00f2 \u00f9");
+        assert_eq!(stop.get_nombre(), "À à â ê ï ô è ì ò ù");
+    }
+
+    #[test]
+    fn test_deserialize_surrogate_pairs() {
+        let stop = stop_named(r"Parada \ud83d\ude8c");
+        assert_eq!(stop.get_nombre(), "Parada 🚌");
+    }
+
+    #[test]
+    fn test_deserialize_windows_1252_body() {
+        // "Rúa 1ª €" encoded as Windows-1252, not valid UTF-8
+        let mut body = br#"{"paradas":[{"parada":1,"nombre":"R"#.to_vec();
+        body.extend_from_slice(b"\xfaa 1\xaa \x80");
+        body.extend_from_slice(br#"","nom_web":"","peso":1}]}"#);
+        let stop = deserialize_stops(crate::utils::fetch_data::decode_body(&body, None))
+            .unwrap()
+            .remove(0);
+        assert_eq!(stop.get_nombre(), "Rúa 1ª €");
+    }
+
+    #[test]
+    fn test_deserialize_malformed_stops() {
+        let result = deserialize_stops(r#"{"stops":[]}"#.to_string());
+        assert!(matches!(result, Err(Error::MalformedStops(_))));
+    }
+
+    #[test]
+    fn test_deserialize_empty_stops() {
+        let result = deserialize_stops(r#"{"paradas":[]}"#.to_string());
+        assert!(matches!(result, Err(Error::EmptyResults("stops"))));
+    }
+
+    #[test]
+    fn test_serialize_schema() {
+        let stop = Stop::new(
+            5714,
+            "Laracha (LARACHA)".to_string(),
+            "Laracha".to_string(),
+            121,
+            Some(43.2492),
+            Some(-8.5872),
+            None,
+            None,
+        );
+        assert_eq!(
+            serde_json::to_value(&stop).unwrap(),
+            serde_json::json!({
+                "id": 5714,
+                "name": "Laracha (LARACHA)",
+                "web_name": "Laracha",
+                "weight": 121,
+                "latitude": 43.2492,
+                "longitude": -8.5872
+            })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_raw_non_ascii() {
+        let stop = stop_named("Ribeira (A POBRA DO CARAMIÑAL) · Praza");
+        assert_eq!(stop.get_nombre(), "Ribeira (A POBRA DO CARAMIÑAL) · Praza");
+    }
+}
@@ -0,0 +1,303 @@
+use std::sync::OnceLock;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// Named styles for everything the interface draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Block titles and the labels of the details
+    pub title: Style,
+    /// Focused borders, key names, the search cursor and the spinner
+    pub highlight: Style,
+    /// Stop and route names
+    pub text: Style,
+    /// Stop ids, separators, dates, typed searches and other secondary text
+    pub muted: Style,
+    /// Table headers
+    pub header: Style,
+    pub error: Style,
+    /// Prices
+    pub fare: Style,
+    /// Departure and arrival times and durations
+    pub time: Style,
+}
+
+/// The built-in themes
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// The colours a theme is built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colors {
+    pub primary: Color,
+    pub secondary: Color,
+    pub error: Color,
+}
+
+impl ThemeName {
+    pub fn colors(self) -> Colors {
+        match self {
+            ThemeName::Dark => Colors {
+                primary: Color::Rgb(51, 202, 214),
+                secondary: Color::Rgb(245, 200, 118),
+                error: Color::Rgb(255, 0, 0),
+            },
+            ThemeName::Light => Colors {
+                primary: Color::Rgb(0, 112, 135),
+                secondary: Color::Rgb(150, 85, 0),
+                error: Color::Rgb(190, 0, 0),
+            },
+            ThemeName::HighContrast => Colors {
+                primary: Color::LightCyan,
+                secondary: Color::LightYellow,
+                error: Color::LightRed,
+            },
+        }
+    }
+}
+
+impl Theme {
+    pub fn new(name: ThemeName, colors: Colors) -> Self {
+        let primary = Style::new().fg(colors.primary);
+        let secondary = Style::new().fg(colors.secondary);
+        let theme = Theme {
+            title: primary.add_modifier(Modifier::BOLD),
+            highlight: primary.add_modifier(Modifier::BOLD),
+            text: primary,
+            muted: secondary,
+            header: secondary.add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            error: Style::new().fg(colors.error),
+            fare: primary.add_modifier(Modifier::ITALIC),
+            time: secondary.add_modifier(Modifier::ITALIC),
+        };
+
+        match name {
+            // nothing relies on a colour alone
+            ThemeName::HighContrast => Theme {
+                text: theme.text.add_modifier(Modifier::BOLD),
+                header: theme.header.add_modifier(Modifier::REVERSED),
+                error: theme.error.add_modifier(Modifier::BOLD),
+                fare: theme.fare.add_modifier(Modifier::BOLD),
+                ..theme
+            },
+            ThemeName::Dark | ThemeName::Light => theme,
+        }
+    }
+
+    /// The same styles with colours the terminal can show
+    pub fn with_depth(self, depth: ColorDepth) -> Self {
+        let convert = |style: Style| Style {
+            fg: style.fg.and_then(|color| depth.convert(color)),
+            bg: style.bg.and_then(|color| depth.convert(color)),
+            ..style
+        };
+        Theme {
+            title: convert(self.title),
+            highlight: convert(self.highlight),
+            text: convert(self.text),
+            muted: convert(self.muted),
+            header: convert(self.header),
+            error: convert(self.error),
+            fare: convert(self.fare),
+            time: convert(self.time),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(ThemeName::Dark, ThemeName::Dark.colors())
+    }
+}
+
+/// How many colours the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    /// `NO_COLOR` is set, only bold, italics and the like are used
+    None,
+}
+
+// the xterm defaults of the 16 ANSI colours
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// the levels of each channel in the 6x6x6 cube of the 256 colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorDepth {
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok();
+        Self::detect(
+            var("NO_COLOR").as_deref(),
+            var("COLORTERM").as_deref(),
+            var("TERM").as_deref(),
+        )
+    }
+
+    /// `NO_COLOR` wins when it is not empty, then `COLORTERM=truecolor|24bit` and a `TERM`
+    /// ending in `256color`
+    pub fn detect(no_color: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            ColorDepth::None
+        } else if matches!(colorterm, Some("truecolor" | "24bit")) {
+            ColorDepth::TrueColor
+        } else if term.is_some_and(|term| term.ends_with("256color")) {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// The closest colour of this depth, `None` leaves the terminal default
+    pub fn convert(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (ColorDepth::None, _) | (_, Color::Reset) => None,
+            (ColorDepth::TrueColor, color) => Some(color),
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Some(Color::Indexed(to_ansi256(r, g, b))),
+            (ColorDepth::Ansi256, color) => Some(color),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => Some(to_ansi16(r, g, b)),
+            (ColorDepth::Ansi16, Color::Indexed(i)) => {
+                let (r, g, b) = indexed_rgb(i);
+                Some(to_ansi16(r, g, b))
+            }
+            (ColorDepth::Ansi16, color) => Some(color),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI_16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+// the closest entry of the colour cube or of the grey ramp
+fn to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = (average.saturating_sub(8) / 10).min(23) as u8;
+    let grey_level = 8 + grey_index * 10;
+    let grey = (grey_level, grey_level, grey_level);
+
+    if distance(grey, (r, g, b)) < distance(cube, (r, g, b)) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI_16[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (i - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// The theme chosen at startup, or the default one
+pub fn theme() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+/// Sets the theme for the rest of the run, only the first call has an effect
+pub fn set_theme(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_depth() {
+        let detect = ColorDepth::detect;
+        assert_eq!(detect(Some("1"), Some("truecolor"), None), ColorDepth::None);
+        assert_eq!(
+            detect(Some(""), Some("truecolor"), None),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect(None, Some("24bit"), None), ColorDepth::TrueColor);
+        assert_eq!(
+            detect(None, None, Some("xterm-256color")),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(detect(None, None, Some("xterm")), ColorDepth::Ansi16);
+        assert_eq!(detect(None, None, None), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_convert_colors() {
+        let cyan = Color::Rgb(51, 202, 214);
+        assert_eq!(ColorDepth::TrueColor.convert(cyan), Some(cyan));
+        assert_eq!(ColorDepth::Ansi256.convert(cyan), Some(Color::Indexed(80)));
+        assert_eq!(ColorDepth::Ansi16.convert(cyan), Some(Color::Cyan));
+        assert_eq!(
+            ColorDepth::Ansi256.convert(Color::Rgb(128, 128, 128)),
+            Some(Color::Indexed(244))
+        );
+        assert_eq!(
+            ColorDepth::Ansi16.convert(Color::Indexed(196)),
+            Some(Color::LightRed)
+        );
+        assert_eq!(ColorDepth::None.convert(Color::Red), None);
+    }
+
+    #[test]
+    fn test_no_color_keeps_modifiers() {
+        let theme = Theme::default().with_depth(ColorDepth::None);
+        assert_eq!(theme.title.fg, None);
+        assert!(theme.title.add_modifier.contains(Modifier::BOLD));
+        assert!(theme.header.add_modifier.contains(Modifier::UNDERLINED));
+    }
+}
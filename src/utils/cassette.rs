@@ -0,0 +1,184 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+use super::json_file::save_json;
+use super::Transport;
+
+/// A single request sent to Arriva and the raw response it got back
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub endpoint: String,
+    pub content_type: String,
+    pub payload: String,
+    pub response: String,
+}
+
+impl Interaction {
+    fn matches(&self, endpoint: &str, content_type: &str, payload: &str) -> bool {
+        self.endpoint == endpoint && self.content_type == content_type && self.payload == payload
+    }
+}
+
+/// Every interaction captured during a session, stored as pretty printed JSON
+/// so two recordings can be diffed when Arriva changes its payloads
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        save_json(path, self)
+    }
+
+    pub fn find(&self, endpoint: &str, content_type: &str, payload: &str) -> Option<&Interaction> {
+        self.interactions
+            .iter()
+            .find(|interaction| interaction.matches(endpoint, content_type, payload))
+    }
+}
+
+/// Forwards every request to the inner transport and writes it to the cassette file
+#[derive(Debug, Clone)]
+pub struct Recorder<T: Transport> {
+    inner: T,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl<T: Transport> Recorder<T> {
+    pub fn new(inner: T, path: PathBuf) -> Self {
+        Self {
+            inner,
+            path,
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+}
+
+impl<T: Transport + Sync> Transport for Recorder<T> {
+    async fn post(&self, endpoint: &str, content_type: &str, body: &str) -> Result<String> {
+        let response = self.inner.post(endpoint, content_type, body).await?;
+
+        let mut cassette = self
+            .cassette
+            .lock()
+            .map_err(|_| Error::Generic("Cassette lock poisoned".to_string()))?;
+        cassette.interactions.push(Interaction {
+            endpoint: endpoint.to_string(),
+            content_type: content_type.to_string(),
+            payload: body.to_string(),
+            response: response.clone(),
+        });
+        // the response is still good when the cassette could not be written
+        if let Err(err) = cassette.save(&self.path) {
+            tracing::warn!(path = %self.path.display(), error = %err, "could not save the cassette");
+        }
+
+        Ok(response)
+    }
+}
+
+/// Answers requests from a previously recorded cassette, failing on anything it has not seen
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    cassette: Arc<Cassette>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            cassette: Arc::new(cassette),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+}
+
+impl Transport for Replayer {
+    async fn post(&self, endpoint: &str, content_type: &str, body: &str) -> Result<String> {
+        match self.cassette.find(endpoint, content_type, body) {
+            Some(interaction) => Ok(interaction.response.clone()),
+            None => Err(Error::UnmatchedRequest {
+                endpoint: endpoint.to_string(),
+                payload: body.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::temp_dir::TempDir;
+    use crate::utils::FixtureTransport;
+
+    const STOPS_ENDPOINT: &str =
+        "https://arriva.gal/plataforma/api/superparadas/index/buscador.json";
+    const STOPS_CONTENT_TYPE: &str = "application/json; charset=UTF-8";
+    const STOPS_PAYLOAD: &str = r#"{"key":"value"}"#;
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = TempDir::new("cassette");
+        let path = dir.join("cassette.json");
+
+        let recorder = Recorder::new(FixtureTransport, path.clone());
+        let recorded = recorder
+            .post(STOPS_ENDPOINT, STOPS_CONTENT_TYPE, STOPS_PAYLOAD)
+            .await
+            .unwrap();
+
+        let replayer = Replayer::from_file(&path).unwrap();
+        let replayed = replayer
+            .post(STOPS_ENDPOINT, STOPS_CONTENT_TYPE, STOPS_PAYLOAD)
+            .await
+            .unwrap();
+        assert_eq!(recorded, replayed);
+    }
+
+    #[tokio::test]
+    async fn test_record_despite_unwritable_cassette() {
+        let dir = TempDir::new("cassette");
+        // a file where the cassette directory should be
+        let file = dir.join("file");
+        fs::write(&file, "").unwrap();
+        let recorder = Recorder::new(FixtureTransport, file.join("cassette.json"));
+        let response = recorder
+            .post(STOPS_ENDPOINT, STOPS_CONTENT_TYPE, STOPS_PAYLOAD)
+            .await;
+        assert!(response.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_unmatched_requests() {
+        let replayer = Replayer::new(Cassette {
+            interactions: vec![Interaction {
+                endpoint: STOPS_ENDPOINT.to_string(),
+                content_type: STOPS_CONTENT_TYPE.to_string(),
+                payload: STOPS_PAYLOAD.to_string(),
+                response: r#"{"paradas":[]}"#.to_string(),
+            }],
+        });
+
+        let response = replayer
+            .post(STOPS_ENDPOINT, STOPS_CONTENT_TYPE, r#"{"key":"other"}"#)
+            .await;
+
+        assert!(matches!(response, Err(Error::UnmatchedRequest { .. })));
+    }
+}
@@ -1,41 +1,170 @@
+use std::time::Instant;
+
+use reqwest::{header, StatusCode};
+
 use crate::prelude::*;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+use super::{Conditional, Validators};
+
+/// Sends one POST through the pooled `client`, tracing how long Arriva took to answer
+pub async fn fetch_data(
+    client: &reqwest::Client,
+    endpoint: &str,
+    content_type: &str,
+    content: &str,
+) -> Result<String> {
+    let validators = Validators::default();
+    match fetch_if_modified(client, endpoint, content_type, content, &validators).await? {
+        Conditional::Modified(body, _) => Ok(body),
+        // only possible when the request carries validators
+        Conditional::NotModified => Err(Error::Status {
+            status: StatusCode::NOT_MODIFIED.as_u16(),
+            endpoint: endpoint.to_string(),
+        }),
+    }
+}
 
-pub async fn fetch_data(endpoint: &str, content_type: &str, content: &str) -> Result<String> {
-    let client = reqwest::Client::new();
+/// Sends one POST with `If-None-Match` and `If-Modified-Since` from `validators`,
+/// returning the body with its new validators unless the server says it did not change
+pub async fn fetch_if_modified(
+    client: &reqwest::Client,
+    endpoint: &str,
+    content_type: &str,
+    content: &str,
+    validators: &Validators,
+) -> Result<Conditional> {
+    let started = Instant::now();
+    let result = send(client, endpoint, content_type, content, validators).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match &result {
+        Ok(Conditional::Modified(body, _)) => {
+            tracing::debug!(endpoint, latency_ms, bytes = body.len(), "request done")
+        }
+        Ok(Conditional::NotModified) => {
+            tracing::debug!(endpoint, latency_ms, "request done, not modified")
+        }
+        Err(err) => tracing::debug!(endpoint, latency_ms, error = %err, "request failed"),
+    }
+    result
+}
 
-    let request = client
+async fn send(
+    client: &reqwest::Client,
+    endpoint: &str,
+    content_type: &str,
+    content: &str,
+    validators: &Validators,
+) -> Result<Conditional> {
+    let mut request = client
         .post(endpoint)
-        .header("Content-Type", content_type)
-        .header("Content-Length", content.len())
-        .header("User-Agent", "curl/8.7.1")
-        .header("Accept", "*/*")
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, content.len())
+        .header(header::ACCEPT, "*/*")
         .body(content.to_owned());
+    if let Some(etag) = &validators.etag {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
 
     let response = request.send().await?;
+    let status = response.status();
+
+    // a POST whose validators match may be answered with 412 instead of 304
+    let has_validators = validators.etag.is_some() || validators.last_modified.is_some();
+    if status == StatusCode::NOT_MODIFIED
+        || (status == StatusCode::PRECONDITION_FAILED && has_validators)
+    {
+        return Ok(Conditional::NotModified);
+    }
+
+    if status.is_success() {
+        let headers = response.headers();
+        let header_text = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        let content_type = header_text(header::CONTENT_TYPE);
+        let validators = Validators {
+            etag: header_text(header::ETAG),
+            last_modified: header_text(header::LAST_MODIFIED),
+        };
+        let bytes = response.bytes().await?;
 
-    if response.status().is_success() {
-        let text = response.text().await?;
-        let text = text.replace("\\u00f1", "ñ");
-        let text = text.replace("\\u00e1", "á");
-        let text = text.replace("\\u00e9", "é");
-        let text = text.replace("\\u00ed", "í");
-        let text = text.replace("\\u00f3", "ó");
-        let text = text.replace("\\u00fa", "ú");
-        let text = text.replace("\\u00fc", "ü");
-        let text = text.replace("\\u00e7", "ç");
-        let text = text.replace("\\u00c1", "Á");
-        let text = text.replace("\\u00c9", "É");
-        let text = text.replace("\\u00cd", "Í");
-        let text = text.replace("\\u00d3", "Ó");
-        let text = text.replace("\\u00da", "Ú");
-        let text = text.replace("\\u00dc", "Ü");
-        let text = text.replace("\\u00c7", "Ç");
-
-        Ok(text)
+        Ok(Conditional::Modified(
+            decode_body(&bytes, content_type.as_deref()),
+            validators,
+        ))
     } else {
-        match response.error_for_status() {
-            Ok(_) => Err(Error::Generic("hola".to_string())),
-            Err(error) => Err(Error::Reqwest(error)),
-        }
+        Err(Error::Status {
+            status: status.as_u16(),
+            endpoint: endpoint.to_string(),
+        })
+    }
+}
+
+/// Decodes a response body using the charset declared in its content type.
+/// Undeclared bodies are read as UTF-8, falling back to Windows-1252 (a superset
+/// of ISO-8859-1) when they are not valid UTF-8. Unicode escapes inside the JSON
+/// are left for serde_json to resolve.
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let declared = content_type
+        .and_then(|content_type| {
+            content_type
+                .split(';')
+                .filter_map(|param| param.trim().split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("charset"))
+        })
+        .and_then(|(_, label)| Encoding::for_label(label.trim().trim_matches('"').as_bytes()));
+
+    let encoding = match declared {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        None => WINDOWS_1252,
+    };
+
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_utf8_without_charset() {
+        let body = "Estación de Coruña".as_bytes();
+        assert_eq!(decode_body(body, None), "Estación de Coruña");
+    }
+
+    #[test]
+    fn test_decode_declared_latin1() {
+        // "Coruña 1ª" encoded as ISO-8859-1
+        let body = [0x43, 0x6f, 0x72, 0x75, 0xf1, 0x61, 0x20, 0x31, 0xaa];
+        assert_eq!(
+            decode_body(&body, Some("application/json; charset=ISO-8859-1")),
+            "Coruña 1ª"
+        );
+    }
+
+    #[test]
+    fn test_decode_undeclared_windows_1252() {
+        // "5€ Rúa" encoded as Windows-1252, not valid UTF-8
+        let body = [0x35, 0x80, 0x20, 0x52, 0xfa, 0x61];
+        assert_eq!(decode_body(&body, Some("application/json")), "5€ Rúa");
+    }
+
+    #[test]
+    fn test_decode_quoted_charset() {
+        let body = "Ourense".as_bytes();
+        assert_eq!(
+            decode_body(body, Some("text/html; charset=\"utf-8\"")),
+            "Ourense"
+        );
     }
 }
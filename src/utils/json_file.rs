@@ -0,0 +1,27 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::prelude::*;
+
+/// Reads a JSON file written by `save_json`, a missing file gives the default value
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes `value` as pretty printed JSON, creating the parent directories. It goes to
+/// a temporary file first and is renamed into place, so a crash never leaves half a file
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
@@ -1,2 +1,15 @@
+pub mod cassette;
 pub mod fetch_data;
-pub use fetch_data::fetch_data;
+pub mod json_file;
+pub mod retry;
+pub mod search;
+#[cfg(test)]
+pub mod temp_dir;
+pub mod transport;
+pub use cassette::{Cassette, Interaction, Recorder, Replayer};
+pub use fetch_data::{fetch_data, fetch_if_modified};
+pub use retry::RetryPolicy;
+pub use search::search_stops;
+pub use transport::{
+    Backend, Conditional, FixtureTransport, ReqwestTransport, Transport, Validators,
+};
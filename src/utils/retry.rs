@@ -0,0 +1,148 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::client::ApiConfig;
+use crate::prelude::*;
+
+/// Longest wait between two attempts, however many retries are configured
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// How many times and how long apart a failed request is sent again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(api: &ApiConfig) -> Self {
+        Self {
+            retries: api.retries,
+            base_delay: Duration::from_millis(api.retry_delay_ms),
+        }
+    }
+
+    /// Wait before retry `retry` (0 based): the base delay doubled on every retry,
+    /// up to `MAX_DELAY`, of which the second half is random so clients that failed
+    /// together do not retry together. `jitter` is in `[0, 1)`
+    pub fn delay(&self, retry: u32, jitter: f64) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(MAX_DELAY);
+        backoff / 2 + backoff.mul_f64(jitter.clamp(0.0, 1.0) / 2.0)
+    }
+
+    /// Runs `request` until it succeeds, fails with an error retrying will not fix or
+    /// runs out of retries
+    pub async fn run<T, F, Fut>(&self, endpoint: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match request().await {
+                Err(err) if retry < self.retries && is_transient(&err) => {
+                    let delay = self.delay(retry, fastrand::f64());
+                    tracing::warn!(
+                        endpoint,
+                        retry = retry + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "retrying request"
+                    );
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Server errors, refused connections and timeouts may work a moment later,
+/// client errors and malformed responses will not
+pub fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Status { status, .. } => *status >= 500,
+        Error::Reqwest(err) => err.is_connect() || err.is_timeout(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    fn status(status: u16) -> Error {
+        Error::Status {
+            status,
+            endpoint: "https://arriva.gal".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_delay_backs_off_with_jitter() {
+        let policy = RetryPolicy {
+            retries: 5,
+            base_delay: Duration::from_millis(200),
+        };
+        assert_eq!(policy.delay(0, 0.0), Duration::from_millis(100));
+        assert_eq!(policy.delay(0, 0.5), Duration::from_millis(150));
+        assert_eq!(policy.delay(2, 0.0), Duration::from_millis(400));
+        assert!(policy.delay(2, 0.99) < Duration::from_millis(800));
+        assert_eq!(policy.delay(20, 1.0), MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let attempts = Cell::new(0);
+        let result = policy(3)
+            .run("stops", || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    match attempt {
+                        1 | 2 => Err(status(503)),
+                        _ => Ok(attempt),
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up() {
+        let attempts = Cell::new(0);
+        let result: Result<()> = policy(2)
+            .run("stops", || {
+                attempts.set(attempts.get() + 1);
+                async { Err(status(502)) }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Status { status: 502, .. })));
+        assert_eq!(attempts.get(), 3);
+
+        // a client error is not retried
+        attempts.set(0);
+        let result: Result<()> = policy(2)
+            .run("stops", || {
+                attempts.set(attempts.get() + 1);
+                async { Err(status(404)) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+}
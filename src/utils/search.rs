@@ -0,0 +1,182 @@
+use crate::prelude::*;
+
+// Bonuses used to rank a fuzzy match, a contiguous substring always beats a scattered one
+const SUBSTRING_BONUS: i64 = 100;
+const WORD_START_BONUS: i64 = 50;
+const TEXT_START_BONUS: i64 = 25;
+const CONSECUTIVE_BONUS: i64 = 5;
+const GAP_PENALTY: i64 = 1;
+
+/// Lowercases `text` and strips the diacritics used in Spanish and Galician,
+/// so "Coruña" and "CORUNA" normalize to the same "coruna"
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' | 'ª' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'º' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            c => c,
+        })
+        .collect()
+}
+
+/// Scores how well every whitespace separated term of `query` matches `text`.
+/// Both must already be normalized. Returns `None` when some term does not match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    query
+        .split_whitespace()
+        .map(|term| term_score(term, text))
+        .sum()
+}
+
+fn term_score(term: &str, text: &str) -> Option<i64> {
+    if let Some(position) = text.find(term) {
+        let mut score = SUBSTRING_BONUS + term.len() as i64;
+        if position == 0 {
+            score += TEXT_START_BONUS;
+        }
+        if position == 0 || !text[..position].ends_with(char::is_alphanumeric) {
+            score += WORD_START_BONUS;
+        }
+        return Some(score);
+    }
+
+    // otherwise every character of the term has to appear in order
+    let mut score = 0;
+    // byte right after the previous matched character
+    let mut last_end: Option<usize> = None;
+    let mut chars = text.char_indices();
+    for wanted in term.chars() {
+        let (index, _) = chars.by_ref().find(|(_, c)| *c == wanted)?;
+        score += match last_end {
+            Some(end) if index == end => CONSECUTIVE_BONUS,
+            Some(end) => -((index - end) as i64 * GAP_PENALTY),
+            None => 0,
+        };
+        score += 1;
+        last_end = Some(index + wanted.len_utf8());
+    }
+    Some(score)
+}
+
+/// Filters the stops matching `query` on either of their names, best matches first
+/// and ties broken by the stop weight. An empty query keeps every stop in order.
+pub fn search_stops(stops: &[Stop], query: &str) -> Vec<Stop> {
+    let query = normalize(query);
+    if query.trim().is_empty() {
+        return stops.to_vec();
+    }
+
+    let mut matches: Vec<(i64, &Stop)> = stops
+        .iter()
+        .filter_map(|stop| {
+            let nombre = fuzzy_score(&query, &normalize(&stop.get_nombre()));
+            let nom_web = fuzzy_score(&query, &normalize(&stop.get_nom_web()));
+            nombre.max(nom_web).map(|score| (score, stop))
+        })
+        .collect();
+
+    matches.sort_by(|(score_a, stop_a), (score_b, stop_b)| {
+        score_b
+            .cmp(score_a)
+            .then_with(|| stop_b.get_peso().cmp(&stop_a.get_peso()))
+    });
+    matches.into_iter().map(|(_, stop)| stop.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(parada: usize, nombre: &str, nom_web: &str, peso: isize) -> Stop {
+        Stop::new(
+            parada,
+            nombre.to_string(),
+            nom_web.to_string(),
+            peso,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn stops() -> Vec<Stop> {
+        vec![
+            stop(5714, "Laracha (LARACHA)", "Laracha", 121),
+            stop(
+                5274,
+                "Estación de Coruña (A CORUÑA)",
+                "Estación de Coruña",
+                516,
+            ),
+            stop(5630, "Carballo (CARBALLO)", "Carballo", 210),
+            stop(6120, "O Carballiño (O CARBALLIÑO)", "O Carballiño", 95),
+        ]
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Estación de CORUÑA"), "estacion de coruna");
+    }
+
+    #[test]
+    fn test_accent_and_case_insensitive() {
+        let found = search_stops(&stops(), "coruna");
+        assert_eq!(found[0].get_parada(), 5274);
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_fuzzy_subsequence() {
+        let found = search_stops(&stops(), "lrch");
+        assert_eq!(found[0].get_parada(), 5714);
+    }
+
+    #[test]
+    fn test_ranking_by_quality_then_weight() {
+        let found = search_stops(&stops(), "carball");
+        let paradas: Vec<usize> = found.iter().map(Stop::get_parada).collect();
+        // both contain the term, but only Carballo starts with it
+        assert_eq!(paradas, vec![5630, 6120]);
+
+        let found = search_stops(&stops(), "carballino");
+        assert_eq!(found[0].get_parada(), 6120);
+
+        let twins = vec![
+            stop(1, "Praza (A)", "Praza", 10),
+            stop(2, "Praza (B)", "Praza", 50),
+        ];
+        let found = search_stops(&twins, "praza");
+        assert_eq!(found[0].get_parada(), 2);
+    }
+
+    #[test]
+    fn test_consecutive_after_multibyte() {
+        // "€" takes three bytes, the "b" right after it is still consecutive
+        assert_eq!(fuzzy_score("€bz", "x€b z"), fuzzy_score("abz", "xab z"));
+    }
+
+    #[test]
+    fn test_multiple_terms() {
+        let found = search_stops(&stops(), "estacion coru");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].get_parada(), 5274);
+    }
+
+    #[test]
+    fn test_no_match() {
+        assert!(search_stops(&stops(), "vigo").is_empty());
+    }
+
+    #[test]
+    fn test_empty_query_keeps_order() {
+        let found = search_stops(&stops(), "  ");
+        assert_eq!(found, stops());
+    }
+}
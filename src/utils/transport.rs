@@ -0,0 +1,196 @@
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::ApiConfig;
+use crate::prelude::*;
+
+use super::{fetch_data, fetch_if_modified, Recorder, Replayer, RetryPolicy};
+
+const FIXTURE_STOPS: &str = include_str!("../../fixtures/buscador.json");
+const FIXTURE_EXPEDITIONS: &str = include_str!("../../fixtures/goSearch.json");
+
+/// The `ETag` and `Last-Modified` a server sent with a response, to ask later
+/// whether it changed
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Answer to a request sent with `Validators`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conditional {
+    Modified(String, Validators),
+    NotModified,
+}
+
+/// Something able to POST a request body to an Arriva endpoint and return the response text
+pub trait Transport: Sync {
+    fn post(
+        &self,
+        endpoint: &str,
+        content_type: &str,
+        body: &str,
+    ) -> impl Future<Output = Result<String>> + Send;
+
+    /// Like `post`, but the server may answer that nothing changed since `validators`.
+    /// Transports without HTTP headers always return the full response
+    fn post_if_modified(
+        &self,
+        endpoint: &str,
+        content_type: &str,
+        body: &str,
+        _validators: &Validators,
+    ) -> impl Future<Output = Result<Conditional>> + Send {
+        async move {
+            let response = self.post(endpoint, content_type, body).await?;
+            Ok(Conditional::Modified(response, Validators::default()))
+        }
+    }
+}
+
+/// Talks to the real Arriva APIs over HTTP. Clones share the same connection pool
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl ReqwestTransport {
+    /// Uses the timeouts, retries and user agent of `api`
+    pub fn new(api: &ApiConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(api.connect_timeout())
+            .read_timeout(api.read_timeout())
+            .user_agent(api.user_agent.as_str())
+            .build()?;
+
+        Ok(Self {
+            client,
+            retry: RetryPolicy::new(api),
+        })
+    }
+}
+
+impl Transport for ReqwestTransport {
+    async fn post(&self, endpoint: &str, content_type: &str, body: &str) -> Result<String> {
+        self.retry
+            .run(endpoint, || {
+                fetch_data(&self.client, endpoint, content_type, body)
+            })
+            .await
+    }
+
+    async fn post_if_modified(
+        &self,
+        endpoint: &str,
+        content_type: &str,
+        body: &str,
+        validators: &Validators,
+    ) -> Result<Conditional> {
+        self.retry
+            .run(endpoint, || {
+                fetch_if_modified(&self.client, endpoint, content_type, body, validators)
+            })
+            .await
+    }
+}
+
+/// Serves the canned responses from `fixtures/` without touching the network
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FixtureTransport;
+
+impl Transport for FixtureTransport {
+    async fn post(&self, endpoint: &str, _content_type: &str, body: &str) -> Result<String> {
+        if endpoint.ends_with("buscador.json") {
+            Ok(FIXTURE_STOPS.to_string())
+        } else if body.contains("method=goSearch") {
+            Ok(FIXTURE_EXPEDITIONS.to_string())
+        } else {
+            Err(Error::UnmatchedRequest {
+                endpoint: endpoint.to_string(),
+                payload: body.to_string(),
+            })
+        }
+    }
+}
+
+/// The transport chosen at startup
+#[derive(Debug, Clone)]
+pub enum Backend {
+    Reqwest(ReqwestTransport),
+    Fixture(FixtureTransport),
+    Record(Recorder<ReqwestTransport>),
+    Replay(Replayer),
+}
+
+impl Transport for Backend {
+    async fn post(&self, endpoint: &str, content_type: &str, body: &str) -> Result<String> {
+        match self {
+            Backend::Reqwest(transport) => transport.post(endpoint, content_type, body).await,
+            Backend::Fixture(transport) => transport.post(endpoint, content_type, body).await,
+            Backend::Record(transport) => transport.post(endpoint, content_type, body).await,
+            Backend::Replay(transport) => transport.post(endpoint, content_type, body).await,
+        }
+    }
+
+    async fn post_if_modified(
+        &self,
+        endpoint: &str,
+        content_type: &str,
+        body: &str,
+        validators: &Validators,
+    ) -> Result<Conditional> {
+        match self {
+            Backend::Reqwest(transport) => {
+                transport
+                    .post_if_modified(endpoint, content_type, body, validators)
+                    .await
+            }
+            Backend::Fixture(transport) => {
+                transport
+                    .post_if_modified(endpoint, content_type, body, validators)
+                    .await
+            }
+            Backend::Record(transport) => {
+                transport
+                    .post_if_modified(endpoint, content_type, body, validators)
+                    .await
+            }
+            Backend::Replay(transport) => {
+                transport
+                    .post_if_modified(endpoint, content_type, body, validators)
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixture_serves_stops() {
+        let response = FixtureTransport
+            .post(
+                "https://arriva.gal/plataforma/api/superparadas/index/buscador.json",
+                "application/json; charset=UTF-8",
+                r#"{"key":"value"}"#,
+            )
+            .await
+            .unwrap();
+
+        assert!(deserialize_stops(response).unwrap().len() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_fixture_rejects_unknown_requests() {
+        let response = FixtureTransport
+            .post("https://example.com", "text/plain", "")
+            .await;
+
+        assert!(response.is_err());
+    }
+}